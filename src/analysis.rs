@@ -0,0 +1,176 @@
+use crate::backtracking::{find_all, find_first};
+use crate::zebra::Zebra;
+use std::fmt::{self, Display};
+
+/// One cell of the solution grid: `object` has `choice` of `property`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Assignment {
+    pub object: usize,
+    pub property: usize,
+    pub choice: usize,
+}
+
+/// Number of solutions containing each assignment.
+#[derive(Debug)]
+pub struct Frequencies {
+    solution_count: usize,
+    // indexed by property, object and choice
+    counts: Vec<Vec<Vec<usize>>>,
+}
+
+impl Frequencies {
+    pub fn get_solution_count(&self) -> usize {
+        self.solution_count
+    }
+
+    pub fn get_count(&self, property: usize, object: usize, choice: usize) -> usize {
+        self.counts[property][object][choice]
+    }
+
+    /// Assignments present in every solution.
+    pub fn get_backbone(&self) -> Vec<Assignment> {
+        if self.solution_count == 0 {
+            return vec![];
+        }
+        self.assignments()
+            .filter(|a| self.get_count(a.property, a.object, a.choice) == self.solution_count)
+            .collect()
+    }
+
+    fn assignments(&self) -> impl Iterator<Item = Assignment> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .flat_map(|(property, objects)| {
+                objects
+                    .iter()
+                    .enumerate()
+                    .flat_map(move |(object, choices)| {
+                        (0..choices.len()).map(move |choice| Assignment {
+                            object,
+                            property,
+                            choice,
+                        })
+                    })
+            })
+    }
+}
+
+/// Enumerates all solutions and counts how many of them contain each
+/// assignment.
+pub fn count_assignments(zebra: &Zebra) -> Frequencies {
+    let (object_count, property_count, counts) = {
+        let props = zebra.get_properties();
+        let counts = (0..props.get_property_count())
+            .map(|p| vec![vec![0; props.get_choice_count(p)]; props.get_object_count()])
+            .collect();
+        (props.get_object_count(), props.get_property_count(), counts)
+    };
+    let mut result = Frequencies {
+        solution_count: 0,
+        counts,
+    };
+    for solution in find_all(zebra.clone()) {
+        result.solution_count += 1;
+        for property in 0..property_count {
+            for object in 0..object_count {
+                let choice = solution.get_determined(property, object).unwrap();
+                result.counts[property][object][choice] += 1;
+            }
+        }
+    }
+    result
+}
+
+/// Computes the assignments which hold in every solution without enumerating
+/// all of them.
+///
+/// Each assignment of one solution is checked by searching for a solution in
+/// which it is forbidden. Returns an empty list when there is no solution.
+pub fn find_backbone(zebra: &Zebra) -> Vec<Assignment> {
+    let first = match find_first(zebra.clone()) {
+        Some(s) => s,
+        None => return vec![],
+    };
+    let (object_count, property_count) = {
+        let props = zebra.get_properties();
+        (props.get_object_count(), props.get_property_count())
+    };
+
+    // Assignments known to differ between two solutions.
+    let mut refuted = vec![false; property_count * object_count];
+    let mut result = vec![];
+    for property in 0..property_count {
+        for object in 0..object_count {
+            if refuted[property * object_count + object] {
+                continue;
+            }
+            let choice = first.get_determined(property, object).unwrap();
+            let mut z = zebra.clone();
+            z.set_choice_enabled(property, object, choice, false);
+            match find_first(z) {
+                Some(other) => {
+                    for p in 0..property_count {
+                        for o in 0..object_count {
+                            if other.get_determined(p, o) != first.get_determined(p, o) {
+                                refuted[p * object_count + o] = true;
+                            }
+                        }
+                    }
+                }
+                None => result.push(Assignment {
+                    object,
+                    property,
+                    choice,
+                }),
+            }
+        }
+    }
+    result
+}
+
+impl Display for Assignment {
+    fn fmt(&self, dest: &mut fmt::Formatter) -> fmt::Result {
+        write!(dest, "{}:{}={}", self.object, self.property, self.choice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::zebra::ZebraBuilder;
+
+    fn make_zebra<'a>() -> Zebra<'a> {
+        // Two solutions: p2b and p2c may be swapped.
+        ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("p1", vec!["p1a", "p1b", "p1c"])
+            .fact("p1", "p1a", "p2", "p2a")
+            .choice("p2", vec!["p2b", "p2c"])
+            .build()
+    }
+
+    #[test]
+    fn test_count_assignments() {
+        let f = count_assignments(&make_zebra());
+        assert_eq!(2, f.get_solution_count());
+        assert_eq!(2, f.get_count(1, 0, 0));
+        assert_eq!(1, f.get_count(1, 1, 1));
+        assert_eq!(1, f.get_count(1, 1, 2));
+        assert_eq!(0, f.get_count(1, 1, 0));
+    }
+
+    #[test]
+    fn test_backbone() {
+        let zebra = make_zebra();
+        let expected: Vec<Assignment> = count_assignments(&zebra).get_backbone();
+        assert_eq!(4, expected.len());
+        assert!(expected.contains(&Assignment {
+            object: 0,
+            property: 1,
+            choice: 0
+        }));
+        assert_eq!(expected, find_backbone(&zebra));
+    }
+}
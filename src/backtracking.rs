@@ -1,12 +1,42 @@
 use std::fmt::Display;
 use std::vec::Vec;
 
+/// Outcome of a propagation round which found no contradiction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Changed {
+    Yes,
+    No,
+}
+
+impl Changed {
+    /// `Yes` unless the count of changes is zero.
+    pub fn from_count(count: i32) -> Changed {
+        if count == 0 {
+            Changed::No
+        } else {
+            Changed::Yes
+        }
+    }
+}
+
+/// The state has no solution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conflict;
+
+/// A puzzle state explored by the search.
+///
+/// States formerly implementing `apply_facts`, `apply_predicates` and
+/// `apply_permutations` call them in that order from `propagate`, failing on
+/// the first one returning false, and rename `split` to `branch`. The search
+/// functions are called as before.
 pub trait State: Sized {
     fn is_solution(&self) -> bool;
-    fn split(&self) -> Vec<Self>;
-    fn apply_facts(&mut self, change_counter: &mut i32) -> bool;
-    fn apply_predicates(&mut self) -> bool;
-    fn apply_permutations(&mut self, change_counter: &mut i32) -> bool;
+    /// Splits an unsolved state into states which together have all of its
+    /// solutions.
+    fn branch(&self) -> Vec<Self>;
+    /// Makes one round of deductions. The search repeats it as long as it
+    /// changes the state.
+    fn propagate(&mut self) -> Result<Changed, Conflict>;
 }
 
 pub static mut COUNTER: i32 = 0;
@@ -15,40 +45,169 @@ pub fn find_first<T>(s: T) -> Option<T>
 where
     T: State + Display + Clone,
 {
-    find_first_from(s, 0)
+    find_first_from(s)
 }
 
-fn find_first_from<T>(ss: T, level: i32) -> Option<T>
+/// Returns every solution reachable from the state, in the order in which
+/// `find_first` would visit them.
+pub fn find_all<T>(s: T) -> Vec<T>
 where
     T: State + Display + Clone,
 {
-    let mut s = ss.clone();
+    let mut result = vec![];
+    find_all_from(s, &mut result);
+    result
+}
+
+/// Counts the solutions reachable from the state without keeping them.
+pub fn count_all<T>(s: T) -> usize
+where
+    T: State,
+{
+    let mut s = s;
     unsafe {
         COUNTER += 1;
     }
 
-    loop {
-        let mut change_counter = 0;
-        let mut valid = s.apply_facts(&mut change_counter);
-        valid = valid && s.apply_predicates();
-        valid = valid && s.apply_permutations(&mut change_counter);
-        if !valid {
-            return None;
+    if !propagate(&mut s) {
+        return 0;
+    }
+    if s.is_solution() {
+        return 1;
+    }
+    s.branch().into_iter().map(count_all).sum()
+}
+
+/// Direction of the optimization done by `find_best`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    Minimize,
+    Maximize,
+}
+
+impl Goal {
+    /// Whether `a` is strictly better than `b`.
+    pub fn is_better(&self, a: i64, b: i64) -> bool {
+        match self {
+            Goal::Minimize => a < b,
+            Goal::Maximize => a > b,
+        }
+    }
+}
+
+/// Returns a solution with the best value of `objective`, or `None` when
+/// there is no solution.
+///
+/// `bound` is called on propagated partial states and must return a value
+/// no solution reachable from the state can beat: an upper bound of the
+/// objective when maximizing, a lower bound when minimizing. States whose
+/// bound is not better than the best solution found so far are skipped.
+pub fn find_best<T, O, B>(s: T, goal: Goal, objective: O, bound: B) -> Option<T>
+where
+    T: State + Clone,
+    O: Fn(&T) -> i64,
+    B: Fn(&T) -> i64,
+{
+    let mut best = None;
+    find_best_from(s, goal, &objective, &bound, &mut best);
+    best.map(|(_, s)| s)
+}
+
+fn find_best_from<T, O, B>(ss: T, goal: Goal, objective: &O, bound: &B, best: &mut Option<(i64, T)>)
+where
+    T: State + Clone,
+    O: Fn(&T) -> i64,
+    B: Fn(&T) -> i64,
+{
+    let mut s = ss;
+    unsafe {
+        COUNTER += 1;
+    }
+
+    if !propagate(&mut s) {
+        return;
+    }
+    if s.is_solution() {
+        let value = objective(&s);
+        let better = match best {
+            Some((b, _)) => goal.is_better(value, *b),
+            None => true,
+        };
+        if better {
+            *best = Some((value, s));
         }
-        if change_counter == 0 {
-            break;
+        return;
+    }
+    if let Some((b, _)) = best {
+        if !goal.is_better(bound(&s), *b) {
+            return;
+        }
+    }
+
+    for c in s.branch() {
+        find_best_from(c, goal, objective, bound, best);
+    }
+}
+
+// Repeats the propagation of the state until it stops changing. Returns false
+// on a conflict.
+pub(crate) fn propagate<T>(s: &mut T) -> bool
+where
+    T: State,
+{
+    loop {
+        match s.propagate() {
+            Err(Conflict) => return false,
+            Ok(Changed::No) => return true,
+            Ok(Changed::Yes) => {}
         }
     }
+}
+
+fn find_first_from<T>(ss: T) -> Option<T>
+where
+    T: State + Display + Clone,
+{
+    let mut s = ss.clone();
+    unsafe {
+        COUNTER += 1;
+    }
+
+    if !propagate(&mut s) {
+        return None;
+    }
     if s.is_solution() {
         return Some(s);
     }
 
-    let children = s.split();
+    let children = s.branch();
     for c in children {
-        let cs = find_first_from(c, level + 1);
+        let cs = find_first_from(c);
         if cs.is_some() {
             return cs;
         }
     }
     None
 }
+
+fn find_all_from<T>(ss: T, result: &mut Vec<T>)
+where
+    T: State + Display + Clone,
+{
+    let mut s = ss.clone();
+    unsafe {
+        COUNTER += 1;
+    }
+
+    if !propagate(&mut s) {
+        return;
+    }
+    if s.is_solution() {
+        result.push(s);
+        return;
+    }
+
+    for c in s.branch() {
+        find_all_from(c, result);
+    }
+}
@@ -1,16 +1,22 @@
 use rust8queens::backtracking::{find_first, COUNTER};
+use rust8queens::diagnosis::find_unsat_core;
 use rust8queens::myzebra::init_my_zebra;
 use rust8queens::zebra::Zebra;
 
 fn main() {
     let zs = init_my_zebra();
     println!("{}", zs);
-    let sol: Option<Zebra> = find_first(zs);
-    if sol.is_some() {
-        unsafe {
-            println!("Solution: {} {}", sol.unwrap(), COUNTER);
-        }
+    let sol: Option<Zebra> = find_first(zs.clone());
+    if let Some(sol) = sol {
+        let counter = unsafe { COUNTER };
+        println!("Solution: {} {}", sol, counter);
     } else {
         println!("No solution");
+        if let Some(core) = find_unsat_core(&zs) {
+            println!("Contradictory clues:");
+            for clue in core {
+                println!("{}: {}", clue, zs.get_clue_text(clue));
+            }
+        }
     }
 }
@@ -0,0 +1,41 @@
+use rust8queens::backtracking::{count_all, find_first, COUNTER};
+use rust8queens::queens::{count_solutions, Queens};
+use std::env;
+use std::time::Instant;
+
+// Largest board counted by the generic search, which clones a state for
+// every node and takes minutes beyond this size.
+const MAX_SEARCH_COUNT_SIZE: usize = 13;
+
+// Solves the N-Queens puzzle for the board size given as the argument, 8 by
+// default, and compares the generic search with the bitboard counter.
+fn main() {
+    let size = match env::args().nth(1) {
+        Some(arg) => arg.parse().expect("Board size should be a number"),
+        None => 8,
+    };
+    match find_first(Queens::new(size)) {
+        Some(solution) => println!("{}", solution),
+        None => println!("No solution"),
+    }
+
+    let start = Instant::now();
+    let count = count_solutions(size);
+    println!("Bitboard count: {} in {:?}", count, start.elapsed());
+
+    if size > MAX_SEARCH_COUNT_SIZE {
+        return;
+    }
+    let start = Instant::now();
+    unsafe {
+        COUNTER = 0;
+    }
+    let count = count_all(Queens::new(size));
+    let counter = unsafe { COUNTER };
+    println!(
+        "Backtracking count: {} in {:?}, {} states",
+        count,
+        start.elapsed(),
+        counter
+    );
+}
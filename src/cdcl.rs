@@ -0,0 +1,355 @@
+/// A conflict-driven clause learning SAT solver. Clauses are added with
+/// DIMACS literals: variable `v` (counted from 1) is `v`, its negation `-v`.
+///
+/// Clauses may be added between calls to `solve`, the clauses learnt so far
+/// are kept.
+#[derive(Debug)]
+pub struct Solver {
+    // Literals are stored as 2 * variable + 1 when negated, variables
+    // counted from 0.
+    clauses: Vec<Vec<usize>>,
+    // Clauses watching each literal, the literal being one of the first two
+    // of the clause.
+    watches: Vec<Vec<usize>>,
+    assignment: Vec<Option<bool>>,
+    level: Vec<usize>,
+    // Clause which implied the value of each variable, its first literal.
+    reason: Vec<Option<usize>>,
+    // Value of each variable the last time it was assigned.
+    phase: Vec<bool>,
+    activity: Vec<f64>,
+    increment: f64,
+    trail: Vec<usize>,
+    // Start of each decision level in `trail`.
+    trail_limits: Vec<usize>,
+    queue_head: usize,
+    conflict_count: usize,
+    unsatisfiable: bool,
+}
+
+fn variable(literal: usize) -> usize {
+    literal >> 1
+}
+
+fn value(assignment: &[Option<bool>], literal: usize) -> Option<bool> {
+    assignment[variable(literal)].map(|v| v != (literal & 1 == 1))
+}
+
+impl Solver {
+    pub fn new(variable_count: usize) -> Solver {
+        Solver {
+            clauses: vec![],
+            watches: vec![vec![]; 2 * variable_count],
+            assignment: vec![None; variable_count],
+            level: vec![0; variable_count],
+            reason: vec![None; variable_count],
+            phase: vec![false; variable_count],
+            activity: vec![0.0; variable_count],
+            increment: 1.0,
+            trail: vec![],
+            trail_limits: vec![],
+            queue_head: 0,
+            conflict_count: 0,
+            unsatisfiable: false,
+        }
+    }
+
+    pub fn get_variable_count(&self) -> usize {
+        self.assignment.len()
+    }
+
+    /// Number of conflicts met by all calls to `solve` so far.
+    pub fn get_conflict_count(&self) -> usize {
+        self.conflict_count
+    }
+
+    /// Adds a clause, returns false once the clauses are known to be
+    /// unsatisfiable.
+    pub fn add_clause(&mut self, clause: &[i32]) -> bool {
+        if self.unsatisfiable {
+            return false;
+        }
+        let mut literals: Vec<usize> = vec![];
+        for &l in clause {
+            let v = l.unsigned_abs() as usize;
+            if l == 0 || v > self.get_variable_count() {
+                panic!("Literal {} is not a variable of the solver", l);
+            }
+            let literal = 2 * (v - 1) + (l < 0) as usize;
+            match value(&self.assignment, literal) {
+                // satisfied on the top level
+                Some(true) => return true,
+                Some(false) => continue,
+                None if literals.contains(&(literal ^ 1)) => return true,
+                None if !literals.contains(&literal) => literals.push(literal),
+                None => {}
+            }
+        }
+        match literals.len() {
+            0 => self.unsatisfiable = true,
+            1 => {
+                self.assign(literals[0], None);
+                if self.propagate().is_some() {
+                    self.unsatisfiable = true;
+                }
+            }
+            _ => {
+                self.attach(literals);
+            }
+        }
+        !self.unsatisfiable
+    }
+
+    /// Returns the value of each variable in a model of the clauses, the
+    /// first variable at index 0, or `None` when there is none.
+    pub fn solve(&mut self) -> Option<Vec<bool>> {
+        if self.unsatisfiable {
+            return None;
+        }
+        let mut restart_limit = 100.0;
+        let mut conflicts = 0;
+        loop {
+            if let Some(conflict) = self.propagate() {
+                self.conflict_count += 1;
+                conflicts += 1;
+                if self.trail_limits.is_empty() {
+                    self.unsatisfiable = true;
+                    return None;
+                }
+                let (learnt, level) = self.analyze(conflict);
+                self.backtrack(level);
+                let first = learnt[0];
+                if learnt.len() == 1 {
+                    self.assign(first, None);
+                } else {
+                    let clause = self.attach(learnt);
+                    self.assign(first, Some(clause));
+                }
+                self.increment /= 0.95;
+                continue;
+            }
+            if conflicts as f64 >= restart_limit {
+                conflicts = 0;
+                restart_limit *= 1.5;
+                self.backtrack(0);
+                continue;
+            }
+            let next = (0..self.get_variable_count())
+                .filter(|v| self.assignment[*v].is_none())
+                .max_by(|a, b| self.activity[*a].total_cmp(&self.activity[*b]));
+            match next {
+                Some(v) => {
+                    self.trail_limits.push(self.trail.len());
+                    self.assign(2 * v + (!self.phase[v]) as usize, None);
+                }
+                None => {
+                    let model = self.assignment.iter().map(|v| v.unwrap()).collect();
+                    self.backtrack(0);
+                    return Some(model);
+                }
+            }
+        }
+    }
+
+    fn attach(&mut self, literals: Vec<usize>) -> usize {
+        let clause = self.clauses.len();
+        self.watches[literals[0]].push(clause);
+        self.watches[literals[1]].push(clause);
+        self.clauses.push(literals);
+        clause
+    }
+
+    fn assign(&mut self, literal: usize, reason: Option<usize>) {
+        let v = variable(literal);
+        self.assignment[v] = Some(literal & 1 == 0);
+        self.level[v] = self.trail_limits.len();
+        self.reason[v] = reason;
+        self.trail.push(literal);
+    }
+
+    // Assigns the literals implied by the clauses, returns a clause whose
+    // literals are all false.
+    fn propagate(&mut self) -> Option<usize> {
+        while self.queue_head < self.trail.len() {
+            let false_literal = self.trail[self.queue_head] ^ 1;
+            self.queue_head += 1;
+            let mut watchers = std::mem::take(&mut self.watches[false_literal]);
+            let mut conflict = None;
+            let mut i = 0;
+            while i < watchers.len() {
+                let c = watchers[i];
+                let clause = &mut self.clauses[c];
+                if clause[0] == false_literal {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                if value(&self.assignment, first) == Some(true) {
+                    i += 1;
+                    continue;
+                }
+                // look for another literal to watch
+                if let Some(k) =
+                    (2..clause.len()).find(|k| value(&self.assignment, clause[*k]) != Some(false))
+                {
+                    clause.swap(1, k);
+                    self.watches[clause[1]].push(c);
+                    watchers.swap_remove(i);
+                    continue;
+                }
+                if value(&self.assignment, first) == Some(false) {
+                    conflict = Some(c);
+                    break;
+                }
+                self.assign(first, Some(c));
+                i += 1;
+            }
+            self.watches[false_literal] = watchers;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    // Learns the clause of the first unique implication point, returns it
+    // with the asserting literal first and the level to jump back to.
+    fn analyze(&mut self, conflict: usize) -> (Vec<usize>, usize) {
+        let current_level = self.trail_limits.len();
+        let mut seen = vec![false; self.get_variable_count()];
+        let mut learnt = vec![0];
+        let mut open = 0;
+        let mut clause = conflict;
+        let mut implied: Option<usize> = None;
+        let mut index = self.trail.len();
+        loop {
+            // the first literal of a reason is the implied one
+            let start = if implied.is_some() { 1 } else { 0 };
+            for k in start..self.clauses[clause].len() {
+                let literal = self.clauses[clause][k];
+                let v = variable(literal);
+                if seen[v] || self.level[v] == 0 {
+                    continue;
+                }
+                seen[v] = true;
+                self.bump(v);
+                if self.level[v] == current_level {
+                    open += 1;
+                } else {
+                    learnt.push(literal);
+                }
+            }
+            loop {
+                index -= 1;
+                if seen[variable(self.trail[index])] {
+                    break;
+                }
+            }
+            let literal = self.trail[index];
+            seen[variable(literal)] = false;
+            implied = Some(literal);
+            open -= 1;
+            if open == 0 {
+                break;
+            }
+            clause = self.reason[variable(literal)].unwrap();
+        }
+        learnt[0] = implied.unwrap() ^ 1;
+        let mut level = 0;
+        for k in 1..learnt.len() {
+            let l = self.level[variable(learnt[k])];
+            if l > level {
+                level = l;
+                // watch the literal which becomes false last
+                learnt.swap(1, k);
+            }
+        }
+        (learnt, level)
+    }
+
+    fn bump(&mut self, v: usize) {
+        self.activity[v] += self.increment;
+        if self.activity[v] > 1e100 {
+            for a in self.activity.iter_mut() {
+                *a *= 1e-100;
+            }
+            self.increment *= 1e-100;
+        }
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.trail_limits.len() <= level {
+            return;
+        }
+        let start = self.trail_limits[level];
+        for &literal in &self.trail[start..] {
+            let v = variable(literal);
+            self.phase[v] = literal & 1 == 0;
+            self.assignment[v] = None;
+            self.reason[v] = None;
+        }
+        self.trail.truncate(start);
+        self.trail_limits.truncate(level);
+        self.queue_head = start;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn satisfies(model: &[bool], clauses: &[Vec<i32>]) -> bool {
+        clauses.iter().all(|clause| {
+            clause
+                .iter()
+                .any(|l| model[l.unsigned_abs() as usize - 1] == (*l > 0))
+        })
+    }
+
+    #[test]
+    fn test_solve() {
+        let clauses = vec![
+            vec![1, 2, 3],
+            vec![-1, -2],
+            vec![-1, -3],
+            vec![-2, -3],
+            vec![-1, 4],
+            vec![-4, 3, 2],
+        ];
+        let mut solver = Solver::new(4);
+        for clause in &clauses {
+            assert!(solver.add_clause(clause));
+        }
+        let model = solver.solve().unwrap();
+        assert!(satisfies(&model, &clauses));
+        // excluding the model changes the answer
+        let blocking: Vec<i32> = (1..=4)
+            .map(|v| if model[v as usize - 1] { -v } else { v })
+            .collect();
+        solver.add_clause(&blocking);
+        let other = solver.solve().unwrap();
+        assert!(satisfies(&other, &clauses));
+        assert_ne!(model, other);
+    }
+
+    #[test]
+    fn test_pigeonhole() {
+        // four pigeons, three holes, variable 3 * pigeon + hole + 1
+        let pigeons = 4;
+        let holes = 3;
+        let var = |p: i32, h: i32| 3 * p + h + 1;
+        let mut solver = Solver::new((pigeons * holes) as usize);
+        for p in 0..pigeons {
+            solver.add_clause(&(0..holes).map(|h| var(p, h)).collect::<Vec<_>>());
+        }
+        for h in 0..holes {
+            for p1 in 0..pigeons {
+                for p2 in p1 + 1..pigeons {
+                    solver.add_clause(&[-var(p1, h), -var(p2, h)]);
+                }
+            }
+        }
+        assert_eq!(None, solver.solve());
+        assert!(solver.get_conflict_count() > 0);
+    }
+}
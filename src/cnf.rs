@@ -0,0 +1,632 @@
+use crate::cdcl::Solver;
+use crate::grounding::{ground_clue, tuples, Formula};
+use crate::reference::is_clue_satisfied;
+use crate::zebra::{Zebra, ZebraProperties};
+use std::fmt::Write;
+
+/// Clauses over boolean variables numbered from 1. A literal is the number
+/// of its variable, negative when negated, as in DIMACS.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cnf {
+    variable_count: usize,
+    clauses: Vec<Vec<i32>>,
+    // Written as comment lines of the DIMACS text.
+    comments: Vec<String>,
+}
+
+impl Cnf {
+    pub fn new(variable_count: usize) -> Cnf {
+        Cnf {
+            variable_count,
+            ..Default::default()
+        }
+    }
+
+    pub fn get_variable_count(&self) -> usize {
+        self.variable_count
+    }
+
+    pub fn get_clauses(&self) -> &[Vec<i32>] {
+        &self.clauses
+    }
+
+    /// Adds an auxiliary variable, returns its literal.
+    pub fn new_variable(&mut self) -> i32 {
+        self.variable_count += 1;
+        self.variable_count as i32
+    }
+
+    pub fn add_clause(&mut self, clause: Vec<i32>) {
+        self.clauses.push(clause);
+    }
+
+    pub fn add_comment(&mut self, comment: String) {
+        self.comments.push(comment);
+    }
+
+    /// At most `k` of the literals are true, using a sequential counter
+    /// unless `k` is 1.
+    pub fn at_most(&mut self, literals: &[i32], k: usize) {
+        let n = literals.len();
+        if k >= n {
+            return;
+        }
+        if k == 0 {
+            for l in literals {
+                self.add_clause(vec![-l]);
+            }
+            return;
+        }
+        if k == 1 {
+            for i in 0..n {
+                for j in i + 1..n {
+                    self.add_clause(vec![-literals[i], -literals[j]]);
+                }
+            }
+            return;
+        }
+        // counter[i][j]: at least j + 1 of the first i + 1 literals are true
+        let counter: Vec<Vec<i32>> = (0..n - 1)
+            .map(|_| (0..k).map(|_| self.new_variable()).collect())
+            .collect();
+        self.add_clause(vec![-literals[0], counter[0][0]]);
+        for l in &counter[0][1..] {
+            self.add_clause(vec![-l]);
+        }
+        for i in 1..n - 1 {
+            self.add_clause(vec![-literals[i], counter[i][0]]);
+            self.add_clause(vec![-counter[i - 1][0], counter[i][0]]);
+            for j in 1..k {
+                self.add_clause(vec![-literals[i], -counter[i - 1][j - 1], counter[i][j]]);
+                self.add_clause(vec![-counter[i - 1][j], counter[i][j]]);
+            }
+            self.add_clause(vec![-literals[i], -counter[i - 1][k - 1]]);
+        }
+        self.add_clause(vec![-literals[n - 1], -counter[n - 2][k - 1]]);
+    }
+
+    /// At least `k` of the literals are true.
+    pub fn at_least(&mut self, literals: &[i32], k: usize) {
+        if k > literals.len() {
+            self.add_clause(vec![]);
+            return;
+        }
+        let negated: Vec<i32> = literals.iter().map(|l| -l).collect();
+        self.at_most(&negated, literals.len() - k);
+    }
+
+    /// Adds a variable true exactly when all the literals are, returns its
+    /// literal.
+    pub fn and_literal(&mut self, literals: &[i32]) -> i32 {
+        let v = self.new_variable();
+        for &l in literals {
+            self.add_clause(vec![-v, l]);
+        }
+        let mut clause: Vec<i32> = literals.iter().map(|l| -l).collect();
+        clause.push(v);
+        self.add_clause(clause);
+        v
+    }
+
+    /// Adds a variable true exactly when some of the literals is, returns
+    /// its literal.
+    pub fn or_literal(&mut self, literals: &[i32]) -> i32 {
+        let negated: Vec<i32> = literals.iter().map(|l| -l).collect();
+        -self.and_literal(&negated)
+    }
+
+    /// Literals, the one at index `k` true exactly when more than `k` of the
+    /// literals are true.
+    pub fn count_literals(&mut self, literals: &[i32]) -> Vec<i32> {
+        let mut result: Vec<i32> = vec![];
+        for &l in literals {
+            let mut next = vec![];
+            for k in 0..=result.len() {
+                // more than k of the literals so far: already before this
+                // one, or this one and k before
+                let with_l = match k {
+                    0 => l,
+                    _ => self.and_literal(&[result[k - 1], l]),
+                };
+                next.push(match result.get(k) {
+                    Some(&before) => self.or_literal(&[before, with_l]),
+                    None => with_l,
+                });
+            }
+            result = next;
+        }
+        result
+    }
+
+    /// The clauses in the DIMACS CNF format read by most SAT solvers.
+    pub fn to_dimacs(&self) -> String {
+        let mut result = String::new();
+        for comment in &self.comments {
+            writeln!(result, "c {}", comment).unwrap();
+        }
+        writeln!(
+            result,
+            "p cnf {} {}",
+            self.variable_count,
+            self.clauses.len()
+        )
+        .unwrap();
+        for clause in &self.clauses {
+            for l in clause {
+                write!(result, "{} ", l).unwrap();
+            }
+            writeln!(result, "0").unwrap();
+        }
+        result
+    }
+}
+
+fn bit_count(props: &ZebraProperties) -> usize {
+    (0..props.get_property_count())
+        .map(|p| props.get_object_count() * props.get_choice_count(p))
+        .sum()
+}
+
+// Literal of the object having the choice, the bit of the choice in
+// `Zebra::values`.
+fn literal(zebra: &Zebra, (property, choice): (usize, usize), object: usize) -> i32 {
+    zebra.index(property, object, choice) as i32 + 1
+}
+
+// Literal true exactly when the formula holds.
+fn formula_literal(cnf: &mut Cnf, zebra: &Zebra, f: &Formula) -> i32 {
+    let mut literals = |items: &[Formula]| -> Vec<i32> {
+        items
+            .iter()
+            .map(|f| formula_literal(cnf, zebra, f))
+            .collect()
+    };
+    match f {
+        Formula::Is(p, o, ch) => literal(zebra, (*p, *ch), *o),
+        Formula::Not(a) => -formula_literal(cnf, zebra, a),
+        Formula::And(items) => {
+            let items = literals(items);
+            cnf.and_literal(&items)
+        }
+        Formula::Or(items) => {
+            let items = literals(items);
+            cnf.or_literal(&items)
+        }
+        Formula::Implies(a, b) => {
+            let a = formula_literal(cnf, zebra, a);
+            let b = formula_literal(cnf, zebra, b);
+            cnf.or_literal(&[-a, b])
+        }
+        Formula::AllDifferent(p) => {
+            let props = zebra.get_properties();
+            let object_count = props.get_object_count();
+            let choices = (0..props.get_choice_count(*p))
+                .map(|ch| {
+                    let owners = (0..object_count).map(|o| Formula::Is(*p, o, ch)).collect();
+                    Formula::Count(owners, 0, 1)
+                })
+                .collect();
+            formula_literal(cnf, zebra, &Formula::And(choices))
+        }
+        Formula::Count(items, min, max) => {
+            let items = literals(items);
+            let counts = cnf.count_literals(&items);
+            let mut bounds = vec![];
+            if *min > items.len() {
+                bounds.push(cnf.or_literal(&[]));
+            } else if *min > 0 {
+                bounds.push(counts[min - 1]);
+            }
+            if *max < items.len() {
+                bounds.push(-counts[*max]);
+            }
+            cnf.and_literal(&bounds)
+        }
+        Formula::Compare(a, comparison, b) => {
+            // defined by the choices of the objects it depends on
+            let mut variables = vec![];
+            a.collect_variables(&mut variables);
+            b.collect_variables(&mut variables);
+            let props = zebra.get_properties();
+            let v = cnf.new_variable();
+            let max_choice_count = variables
+                .iter()
+                .map(|(p, _)| props.get_choice_count(*p))
+                .max()
+                .unwrap_or(1);
+            for choices in tuples(max_choice_count, variables.len()) {
+                if variables.iter().zip(&choices).any(|(&(p, o), &ch)| {
+                    ch >= props.get_choice_count(p) || !zebra.is_choice_enabled(p, o, ch)
+                }) {
+                    continue;
+                }
+                let choice = |p: usize, o: usize| {
+                    let i = variables.iter().position(|x| *x == (p, o)).unwrap();
+                    choices[i]
+                };
+                let holds =
+                    comparison.holds(a.evaluate(&props, &choice), b.evaluate(&props, &choice));
+                let mut clause: Vec<i32> = variables
+                    .iter()
+                    .zip(&choices)
+                    .map(|(&(p, o), &ch)| -literal(zebra, (p, ch), o))
+                    .collect();
+                clause.push(if holds { v } else { -v });
+                cnf.add_clause(clause);
+            }
+            v
+        }
+    }
+}
+
+// Adds clauses making the formula hold.
+fn add_formula(cnf: &mut Cnf, zebra: &Zebra, f: &Formula) {
+    match f {
+        Formula::And(items) => {
+            for item in items {
+                add_formula(cnf, zebra, item);
+            }
+        }
+        Formula::Or(items) => {
+            let clause = items
+                .iter()
+                .map(|f| formula_literal(cnf, zebra, f))
+                .collect();
+            cnf.add_clause(clause);
+        }
+        Formula::Implies(a, b) => {
+            let mut clause = vec![-formula_literal(cnf, zebra, a)];
+            match b.as_ref() {
+                Formula::Or(items) => {
+                    for item in items {
+                        clause.push(formula_literal(cnf, zebra, item));
+                    }
+                }
+                b => clause.push(formula_literal(cnf, zebra, b)),
+            }
+            cnf.add_clause(clause);
+        }
+        f => {
+            let l = formula_literal(cnf, zebra, f);
+            cnf.add_clause(vec![l]);
+        }
+    }
+}
+
+/// Encodes the puzzle from the state as CNF with one variable per bit of
+/// the `Zebra` domain, plus auxiliary ones: every object has exactly one
+/// choice of each property and every choice is held by as many objects as
+/// it may. The clues become clauses through their formulas, see
+/// `grounding::ground_clue`, except arithmetic clues selecting objects by a
+/// shared choice, left to `find_first_sat`, which checks them on the models.
+pub fn encode(zebra: &Zebra) -> Cnf {
+    let props = zebra.get_properties();
+    let object_count = props.get_object_count();
+    let property_count = props.get_property_count();
+    let mut cnf = Cnf::new(bit_count(&props));
+
+    for p in 0..property_count {
+        let choice_count = props.get_choice_count(p);
+        for object in 0..object_count {
+            let cell: Vec<i32> = (0..choice_count)
+                .map(|ch| literal(zebra, (p, ch), object))
+                .collect();
+            for (ch, &l) in cell.iter().enumerate() {
+                cnf.add_comment(format!(
+                    "{} {} {}={}",
+                    l,
+                    props.get_object_name(object),
+                    props.get_property_name(p),
+                    props.get_property_choice_name_by_nr(p, ch)
+                ));
+                if !zebra.is_choice_enabled(p, object, ch) {
+                    cnf.add_clause(vec![-l]);
+                }
+            }
+            cnf.add_clause(cell.clone());
+            cnf.at_most(&cell, 1);
+        }
+        for ch in 0..choice_count {
+            if zebra.is_choice_exempt(p, ch) {
+                continue;
+            }
+            let column: Vec<i32> = (0..object_count)
+                .map(|o| literal(zebra, (p, ch), o))
+                .collect();
+            let (min, max) = props.get_occurrence(p, ch);
+            cnf.at_most(&column, max);
+            cnf.at_least(&column, min);
+        }
+    }
+
+    for &(rows, columns) in &props.grids {
+        for o1 in 0..object_count {
+            for o2 in o1 + 1..object_count {
+                for row in 0..props.get_choice_count(rows) {
+                    for column in 0..props.get_choice_count(columns) {
+                        cnf.add_clause(vec![
+                            -literal(zebra, (rows, row), o1),
+                            -literal(zebra, (columns, column), o1),
+                            -literal(zebra, (rows, row), o2),
+                            -literal(zebra, (columns, column), o2),
+                        ]);
+                    }
+                }
+            }
+        }
+    }
+
+    for clue in 0..zebra.get_clue_count() {
+        if !zebra.is_clue_enforced(clue) {
+            continue;
+        }
+        if let Some(formulas) = ground_clue(zebra, clue) {
+            for f in &formulas {
+                add_formula(&mut cnf, zebra, f);
+            }
+        }
+    }
+    cnf
+}
+
+/// The state of the puzzle given by the values of the variables of
+/// `encode`, the first variable at index 0.
+pub fn decode<'a>(zebra: &Zebra<'a>, model: &[bool]) -> Zebra<'a> {
+    let mut result = zebra.clone();
+    let props = zebra.get_properties();
+    for p in 0..props.get_property_count() {
+        for object in 0..props.get_object_count() {
+            for ch in 0..props.get_choice_count(p) {
+                let l = literal(zebra, (p, ch), object);
+                result.set_choice_enabled(p, object, ch, model[l as usize - 1]);
+            }
+        }
+    }
+    result
+}
+
+/// Like `find_first`, but solves the CNF of `encode` with the built-in
+/// CDCL solver. Models breaking a clue without clauses, see `encode`, as
+/// checked by `reference::check_clue`, are excluded one by one, so puzzles
+/// relying on such clues are solved slowly.
+pub fn find_first_sat(zebra: Zebra) -> Option<Zebra> {
+    let unencoded: Vec<usize> = (0..zebra.get_clue_count())
+        .filter(|c| zebra.is_clue_enforced(*c) && ground_clue(&zebra, *c).is_none())
+        .collect();
+    let cnf = encode(&zebra);
+    let mut solver = Solver::new(cnf.get_variable_count());
+    for clause in cnf.get_clauses() {
+        if !solver.add_clause(clause) {
+            return None;
+        }
+    }
+    let bit_count = bit_count(&zebra.get_properties());
+    loop {
+        let model = solver.solve()?;
+        let result = decode(&zebra, &model);
+        if unencoded.iter().all(|c| is_clue_satisfied(&result, *c)) {
+            return Some(result);
+        }
+        let blocking: Vec<i32> = (1..=bit_count as i32)
+            .filter(|v| model[*v as usize - 1])
+            .map(|v| -v)
+            .collect();
+        if !solver.add_clause(&blocking) {
+            return None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::backtracking::{count_all, find_first};
+    use crate::expression::{Expr, Selector};
+    use crate::literal::Literal;
+    use crate::myzebra::init_my_zebra;
+    use crate::relation::Comparison;
+    use crate::relation::Relation;
+    use crate::zebra::ZebraBuilder;
+
+    #[test]
+    fn test_to_dimacs() {
+        let mut cnf = Cnf::new(2);
+        cnf.add_comment("two variables".to_string());
+        cnf.add_clause(vec![1, -2]);
+        cnf.add_clause(vec![2]);
+        assert_eq!("c two variables\np cnf 2 2\n1 -2 0\n2 0\n", cnf.to_dimacs());
+    }
+
+    #[test]
+    fn test_at_most() {
+        // exactly two of four
+        let mut cnf = Cnf::new(4);
+        cnf.at_most(&[1, 2, 3, 4], 2);
+        cnf.at_least(&[1, 2, 3, 4], 2);
+        let mut solver = Solver::new(cnf.get_variable_count());
+        for clause in cnf.get_clauses() {
+            solver.add_clause(clause);
+        }
+        let mut count = 0;
+        while let Some(model) = solver.solve() {
+            assert_eq!(2, model[..4].iter().filter(|v| **v).count());
+            let blocking: Vec<i32> = (1..=4)
+                .map(|v| if model[v as usize - 1] { -v } else { v })
+                .collect();
+            solver.add_clause(&blocking);
+            count += 1;
+        }
+        assert_eq!(6, count);
+    }
+
+    #[test]
+    fn test_find_first_sat() {
+        let zebra = init_my_zebra();
+        let expected = find_first(zebra.clone()).unwrap();
+        assert_eq!(
+            format!("{}", expected),
+            format!("{}", find_first_sat(zebra).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_find_first_sat_unsolvable() {
+        let zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("place", vec!["1", "2", "3"])
+            .choice("name", vec!["Ann", "Bob", "Cid"])
+            .relation(
+                Relation::LeftOf,
+                "place",
+                vec![("name", "Ann"), ("name", "Bob")],
+            )
+            .relation(
+                Relation::LeftOf,
+                "place",
+                vec![("name", "Bob"), ("name", "Cid")],
+            )
+            .relation(
+                Relation::LeftOf,
+                "place",
+                vec![("name", "Cid"), ("name", "Ann")],
+            )
+            .build();
+        assert!(find_first_sat(zebra).is_none());
+    }
+
+    // Number of models of the CNF, counting the variables of `Zebra::values`
+    // only.
+    fn count_models(zebra: &Zebra) -> usize {
+        let cnf = encode(zebra);
+        let mut solver = Solver::new(cnf.get_variable_count());
+        for clause in cnf.get_clauses() {
+            if !solver.add_clause(clause) {
+                return 0;
+            }
+        }
+        let bit_count = bit_count(&zebra.get_properties()) as i32;
+        let mut count = 0;
+        while let Some(model) = solver.solve() {
+            count += 1;
+            let blocking: Vec<i32> = (1..=bit_count)
+                .map(|v| if model[v as usize - 1] { -v } else { v })
+                .collect();
+            if !solver.add_clause(&blocking) {
+                break;
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn test_encode_compound_clues() {
+        // the models are exactly the solutions, without checking them
+        let zebra = ZebraBuilder::new()
+            .set_object_count(4)
+            .choice("name", vec!["Ann", "Bob", "Cid", "Dan"])
+            .numeric("age", vec!["20", "30", "40", "50"])
+            .choice("pet", vec!["cat", "dog", "fox", "owl"])
+            .choice("place", vec!["1", "2", "3", "4"])
+            .predicate(
+                "name",
+                "Bob",
+                "pet",
+                "cat",
+                "age",
+                "age",
+                Box::new(|a, b| a < b),
+            )
+            .either(vec![
+                Literal::fact("name", "Ann", "pet", "fox"),
+                Literal::relation(Relation::AtEnd, "place", vec![("pet", "dog")]),
+            ])
+            .implies(
+                Literal::not_fact("name", "Bob", "place", "1"),
+                Literal::relation(
+                    Relation::Between,
+                    "place",
+                    vec![("age", "30"), ("pet", "fox"), ("name", "Ann")],
+                ),
+            )
+            .at_most(
+                1,
+                vec![
+                    Literal::fact("pet", "cat", "age", "20"),
+                    Literal::relation(
+                        Relation::LeftOf,
+                        "place",
+                        vec![("name", "Bob"), ("age", "30")],
+                    ),
+                ],
+            )
+            .object_cardinality(
+                vec![("pet", vec!["cat", "fox"]), ("age", vec!["20", "30"])],
+                1,
+                1,
+            )
+            .compare("age", Comparison::Greater, ("name", "Cid"), ("pet", "owl"))
+            .arithmetic(
+                Expr::value("age", Selector::has("name", "Ann").offset("place", 1)),
+                Comparison::Greater,
+                Expr::value("age", Selector::has("name", "Dan")) - Expr::Const(10),
+            )
+            .build();
+        let expected = count_all(zebra.clone());
+        assert!(expected > 1);
+        assert_eq!(expected, count_models(&zebra));
+    }
+
+    #[test]
+    fn test_find_first_sat_predicates() {
+        let zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("name", vec!["Ann", "Bob", "Cid"])
+            .choice("age", vec!["20", "30", "40"])
+            .predicate(
+                "name",
+                "Ann",
+                "name",
+                "Bob",
+                "age",
+                "age",
+                Box::new(|a, b| a > b),
+            )
+            .predicate(
+                "name",
+                "Bob",
+                "name",
+                "Cid",
+                "age",
+                "age",
+                Box::new(|a, b| a > b),
+            )
+            .build();
+        let solution = find_first_sat(zebra).unwrap();
+        assert_eq!(Some("40"), solution.get_determined_name("Ann", "age"));
+        assert_eq!(Some("20"), solution.get_determined_name("Cid", "age"));
+    }
+
+    #[test]
+    fn test_find_first_sat_unencoded_clue() {
+        // the age of a red object, one of several, has no clauses
+        let zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("name", vec!["Ann", "Bob", "Cid"])
+            .numeric("age", vec!["20", "30", "40"])
+            .choice("color", vec!["red", "green"])
+            .occurrence("color", vec!["red", "green"], 0, 3)
+            .fact("name", "Ann", "color", "green")
+            .arithmetic(
+                Expr::value("age", Selector::has("color", "red")),
+                Comparison::Greater,
+                Expr::Const(35),
+            )
+            .build();
+        assert!(ground_clue(&zebra, 4).is_none());
+        let solution = find_first_sat(zebra).unwrap();
+        let oldest = solution.get_owner_name("age", "40").unwrap();
+        assert_ne!("Ann", oldest);
+        assert_eq!(Some("red"), solution.get_determined_name(oldest, "color"));
+    }
+}
@@ -0,0 +1,81 @@
+use crate::backtracking::find_first;
+use crate::zebra::Zebra;
+
+/// Finds a minimal set of clues which together have no solution.
+///
+/// Returns `None` when the puzzle can be solved. Otherwise the result lists
+/// clue indexes such that the clues are contradictory, but removing any one
+/// of them makes the rest solvable. The puzzle should be passed as returned by
+/// `ZebraBuilder::build`, before any propagation took place. Clues which are
+/// disabled on input are not considered. The clues are toggled on a clone, the
+/// input keeps its setting.
+pub fn find_unsat_core(zebra: &Zebra) -> Option<Vec<usize>> {
+    if find_first(zebra.clone()).is_some() {
+        return None;
+    }
+
+    let mut z = zebra.clone();
+    let mut core = vec![];
+    for clue in 0..z.get_clue_count() {
+        if !z.is_clue_enabled(clue) {
+            continue;
+        }
+        z.set_clue_enabled(clue, false);
+        if find_first(z.clone()).is_some() {
+            // The contradiction disappears without this clue.
+            z.set_clue_enabled(clue, true);
+            core.push(clue);
+        }
+    }
+    Some(core)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::myzebra::init_my_zebra;
+    use crate::zebra::ZebraBuilder;
+
+    #[test]
+    fn test_solvable_puzzle_has_no_core() {
+        assert_eq!(None, find_unsat_core(&init_my_zebra()));
+    }
+
+    #[test]
+    fn test_unsat_core() {
+        let zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("p1", vec!["p1a", "p1b", "p1c"])
+            .fact("p1", "p1a", "p2", "p2a")
+            .fact("p3", "p3a", "p2", "p2c")
+            .fact("p1", "p1b", "p2", "p2b")
+            .fact("p1", "p1a", "p3", "p3a")
+            .choice("p3", vec!["p3b", "p3c"])
+            .build();
+
+        let core = find_unsat_core(&zebra).unwrap();
+        assert_eq!(vec![1, 2, 4], core);
+        assert_eq!("fact: p3=p3a, p2=p2c", zebra.get_clue_text(2));
+        for clue in 0..zebra.get_clue_count() {
+            assert!(zebra.is_clue_enabled(clue));
+        }
+    }
+
+    #[test]
+    fn test_clue_enabled_per_clone() {
+        let zebra = ZebraBuilder::new()
+            .set_object_count(2)
+            .choice("p1", vec!["p1a", "p1b"])
+            .fact("p1", "p1a", "p2", "p2a")
+            .fact("p1", "p1a", "p2", "p2b")
+            .choice("p2", vec!["p2a", "p2b"])
+            .build();
+        let mut relaxed = zebra.clone();
+        relaxed.set_clue_enabled(2, false);
+        assert!(zebra.is_clue_enabled(2));
+        assert!(find_first(relaxed).is_some());
+        assert!(find_first(zebra.clone()).is_none());
+        assert_eq!(Some(vec![1, 2]), find_unsat_core(&zebra));
+    }
+}
@@ -0,0 +1,679 @@
+use crate::grounding::{ground_clue, not, Formula, Number};
+use crate::relation::Comparison;
+use crate::zebra::{ClueKind, Zebra};
+use std::fmt::Write;
+
+// Groups of constraints, each with a comment, and the texts of the clues
+// which cannot be exported.
+struct Model {
+    groups: Vec<(String, Vec<Formula>)>,
+    skipped: Vec<String>,
+}
+
+fn model(zebra: &Zebra) -> Model {
+    let props = zebra.get_properties();
+    let object_count = props.get_object_count();
+    let mut groups = vec![];
+
+    let mut domain = vec![];
+    for p in 0..props.get_property_count() {
+        for o in 0..object_count {
+            for ch in 0..props.get_choice_count(p) {
+                if !zebra.is_choice_enabled(p, o, ch) {
+                    domain.push(not(Formula::Is(p, o, ch)));
+                }
+            }
+        }
+    }
+    groups.push(("known choices".to_string(), domain));
+
+    let mut counts = vec![];
+    for p in 0..props.get_property_count() {
+        let choice_count = props.get_choice_count(p);
+        let exempt = (0..choice_count).any(|ch| zebra.is_choice_exempt(p, ch));
+        if !exempt
+            && choice_count == object_count
+            && (0..choice_count).all(|ch| props.get_occurrence(p, ch) == (1, 1))
+        {
+            counts.push(Formula::AllDifferent(p));
+            continue;
+        }
+        for ch in (0..choice_count).filter(|ch| !zebra.is_choice_exempt(p, *ch)) {
+            let (min, max) = props.get_occurrence(p, ch);
+            if min > 0 || max < object_count {
+                let owners = (0..object_count).map(|o| Formula::Is(p, o, ch)).collect();
+                counts.push(Formula::Count(owners, min, max));
+            }
+        }
+    }
+    groups.push(("owners of the choices".to_string(), counts));
+
+    let mut cells = vec![];
+    for &(rows, columns) in &props.grids {
+        let differ = |p: usize, o1: usize, o2: usize| {
+            Formula::Compare(
+                Number::Choice(p, o1),
+                Comparison::NotEqual,
+                Number::Choice(p, o2),
+            )
+        };
+        for o1 in 0..object_count {
+            for o2 in o1 + 1..object_count {
+                cells.push(Formula::Or(vec![
+                    differ(rows, o1, o2),
+                    differ(columns, o1, o2),
+                ]));
+            }
+        }
+    }
+    if !cells.is_empty() {
+        groups.push(("one object per cell of the grids".to_string(), cells));
+    }
+
+    let mut skipped = vec![];
+    for clue in 0..zebra.get_clue_count() {
+        if zebra.get_clue_kind(clue) == ClueKind::Choice || !zebra.is_clue_enabled(clue) {
+            continue;
+        }
+        match ground_clue(zebra, clue) {
+            Some(formulas) if zebra.is_clue_enforced(clue) => {
+                groups.push((zebra.get_clue_text(clue), formulas));
+            }
+            _ => skipped.push(zebra.get_clue_text(clue)),
+        }
+    }
+    Model { groups, skipped }
+}
+
+// A MiniZinc and SMT-LIB identifier for the property name.
+fn identifier(name: &str) -> String {
+    let result: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if result.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        result
+    } else {
+        format!("p_{}", result)
+    }
+}
+
+// Lines of comments naming the objects and the choices.
+fn legend(zebra: &Zebra) -> Vec<String> {
+    let props = zebra.get_properties();
+    let mut result = vec![];
+    let objects: Vec<String> = (0..props.get_object_count())
+        .map(|o| format!("{} = {}", o, props.get_object_name(o)))
+        .collect();
+    result.push(format!("objects: {}", objects.join(", ")));
+    for p in 0..props.get_property_count() {
+        let choices: Vec<String> = (0..props.get_choice_count(p))
+            .map(|ch| format!("{} = {}", ch, props.get_property_choice_name_by_nr(p, ch)))
+            .collect();
+        result.push(format!(
+            "{}: {}",
+            identifier(props.get_property_name(p)),
+            choices.join(", ")
+        ));
+    }
+    result
+}
+
+// Whether the values of the property are other than the numbers of its
+// choices, as for numeric properties.
+fn has_own_values(zebra: &Zebra, property: usize) -> bool {
+    let props = zebra.get_properties();
+    (0..props.get_choice_count(property)).any(|ch| props.get_value(property, ch) != ch as i64)
+}
+
+// The choices of the objects owning the choice, when the formulas test
+// nothing else.
+fn owners_of(zebra: &Zebra, items: &[Formula]) -> Option<(usize, usize)> {
+    let object_count = zebra.get_properties().get_object_count();
+    match items.first() {
+        Some(&Formula::Is(p, _, ch)) if items.len() == object_count => {
+            let owners = (0..object_count).all(|o| items[o] == Formula::Is(p, o, ch));
+            owners.then_some((p, ch))
+        }
+        _ => None,
+    }
+}
+
+// Whether a product multiplies two variables, which needs non-linear
+// arithmetic.
+fn is_linear(n: &Number) -> bool {
+    match n {
+        Number::Const(_) | Number::Choice(..) | Number::Value(..) => true,
+        Number::Mul(a, b) => {
+            is_linear(a)
+                && is_linear(b)
+                && (matches!(a.as_ref(), Number::Const(_))
+                    || matches!(b.as_ref(), Number::Const(_)))
+        }
+        Number::Add(a, b) | Number::Sub(a, b) => is_linear(a) && is_linear(b),
+        Number::Abs(a) | Number::Mod(a, _) => is_linear(a),
+    }
+}
+
+fn is_linear_formula(f: &Formula) -> bool {
+    match f {
+        Formula::Is(..) | Formula::AllDifferent(_) => true,
+        Formula::Not(a) => is_linear_formula(a),
+        Formula::And(items) | Formula::Or(items) | Formula::Count(items, _, _) => {
+            items.iter().all(is_linear_formula)
+        }
+        Formula::Implies(a, b) => is_linear_formula(a) && is_linear_formula(b),
+        Formula::Compare(a, _, b) => is_linear(a) && is_linear(b),
+    }
+}
+
+fn minizinc_number(zebra: &Zebra, n: &Number) -> String {
+    let name = |p: usize| identifier(zebra.get_properties().get_property_name(p));
+    let pair = |a: &Number, op: &str, b: &Number| {
+        format!(
+            "({} {} {})",
+            minizinc_number(zebra, a),
+            op,
+            minizinc_number(zebra, b)
+        )
+    };
+    match n {
+        Number::Const(c) => c.to_string(),
+        Number::Value(p, o) if has_own_values(zebra, *p) => {
+            format!("{}_value[{}[{}]]", name(*p), name(*p), o)
+        }
+        Number::Choice(p, o) | Number::Value(p, o) => format!("{}[{}]", name(*p), o),
+        Number::Add(a, b) => pair(a, "+", b),
+        Number::Sub(a, b) => pair(a, "-", b),
+        Number::Mul(a, b) => pair(a, "*", b),
+        Number::Abs(a) => format!("abs({})", minizinc_number(zebra, a)),
+        Number::Mod(a, n) => format!("({} mod {})", minizinc_number(zebra, a), n),
+    }
+}
+
+fn minizinc_comparison(a: String, comparison: Comparison, b: String) -> String {
+    match comparison {
+        Comparison::Less => format!("{} < {}", a, b),
+        Comparison::LessOrEqual => format!("{} <= {}", a, b),
+        Comparison::Greater => format!("{} > {}", a, b),
+        Comparison::GreaterOrEqual => format!("{} >= {}", a, b),
+        Comparison::Equal => format!("{} = {}", a, b),
+        Comparison::NotEqual => format!("{} != {}", a, b),
+        Comparison::Difference(k) if k < 0 => format!("{} - {} = {}", b, a, -k),
+        Comparison::Difference(k) => format!("{} - {} = {}", a, b, k),
+        Comparison::AbsDifference(k) => format!("abs({} - {}) = {}", a, b, k),
+    }
+}
+
+fn minizinc_formula(zebra: &Zebra, f: &Formula) -> String {
+    let name = |p: usize| identifier(zebra.get_properties().get_property_name(p));
+    let join = |items: &[Formula], op: &str| {
+        let items: Vec<String> = items.iter().map(|f| minizinc_formula(zebra, f)).collect();
+        format!("({})", items.join(op))
+    };
+    match f {
+        Formula::And(items) if items.is_empty() => "true".to_string(),
+        Formula::Or(items) if items.is_empty() => "false".to_string(),
+        Formula::Is(p, o, ch) => format!("{}[{}] = {}", name(*p), o, ch),
+        Formula::Not(a) => match a.as_ref() {
+            Formula::Is(p, o, ch) => format!("{}[{}] != {}", name(*p), o, ch),
+            a @ Formula::Compare(..) => format!("not ({})", minizinc_formula(zebra, a)),
+            a => format!("not {}", minizinc_formula(zebra, a)),
+        },
+        Formula::And(items) => join(items, " /\\ "),
+        Formula::Or(items) => join(items, " \\/ "),
+        Formula::Implies(a, b) => format!(
+            "({} -> {})",
+            minizinc_formula(zebra, a),
+            minizinc_formula(zebra, b)
+        ),
+        Formula::AllDifferent(p) => format!("alldifferent({})", name(*p)),
+        Formula::Count(items, min, max) => match owners_of(zebra, items) {
+            Some((p, ch)) => format!("count({}, {}) in {}..{}", name(p), ch, min, max),
+            None => {
+                let terms: Vec<String> = items
+                    .iter()
+                    .map(|f| format!("bool2int({})", minizinc_formula(zebra, f)))
+                    .collect();
+                let sum = match terms.len() {
+                    0 => "0".to_string(),
+                    _ => format!("({})", terms.join(" + ")),
+                };
+                format!("{} in {}..{}", sum, min, max)
+            }
+        },
+        Formula::Compare(a, comparison, b) => minizinc_comparison(
+            minizinc_number(zebra, a),
+            *comparison,
+            minizinc_number(zebra, b),
+        ),
+    }
+}
+
+/// Writes the puzzle from the state as a MiniZinc model with one integer
+/// variable per object and property holding the number of its choice, the
+/// place of the object in an ordered property. Relations and comparisons
+/// are arithmetic on these variables, numeric values are looked up in
+/// arrays. Soft clues and arithmetic clues selecting objects by a shared
+/// choice are not exported, they are listed in comments.
+pub fn to_minizinc(zebra: &Zebra) -> String {
+    let props = zebra.get_properties();
+    let model = model(zebra);
+    let mut result = String::new();
+    for line in legend(zebra) {
+        writeln!(result, "% {}", line).unwrap();
+    }
+    writeln!(result, "include \"globals.mzn\";").unwrap();
+    let object_count = props.get_object_count();
+    for p in 0..props.get_property_count() {
+        let name = identifier(props.get_property_name(p));
+        let choice_count = props.get_choice_count(p);
+        writeln!(
+            result,
+            "array[0..{}] of var 0..{}: {};",
+            object_count - 1,
+            choice_count - 1,
+            name
+        )
+        .unwrap();
+        if has_own_values(zebra, p) {
+            let values: Vec<String> = (0..choice_count)
+                .map(|ch| props.get_value(p, ch).to_string())
+                .collect();
+            writeln!(
+                result,
+                "array[0..{}] of int: {}_value = array1d(0..{}, [{}]);",
+                choice_count - 1,
+                name,
+                choice_count - 1,
+                values.join(", ")
+            )
+            .unwrap();
+        }
+    }
+    for (comment, formulas) in &model.groups {
+        writeln!(result, "% {}", comment).unwrap();
+        for f in formulas {
+            writeln!(result, "constraint {};", minizinc_formula(zebra, f)).unwrap();
+        }
+    }
+    for text in &model.skipped {
+        writeln!(result, "% not exported: {}", text).unwrap();
+    }
+    writeln!(result, "solve satisfy;").unwrap();
+    let outputs: Vec<String> = (0..props.get_property_count())
+        .map(|p| {
+            let name = identifier(props.get_property_name(p));
+            format!("\"{} = \\({})\\n\"", name, name)
+        })
+        .collect();
+    writeln!(result, "output [{}];", outputs.join(", ")).unwrap();
+    result
+}
+
+fn smtlib_number(zebra: &Zebra, n: &Number) -> String {
+    let props = zebra.get_properties();
+    let variable = |p: usize, o: usize| format!("{}_{}", identifier(props.get_property_name(p)), o);
+    let constant = |c: i64| match c {
+        c if c < 0 => format!("(- {})", -c),
+        c => c.to_string(),
+    };
+    let pair = |op: &str, a: &Number, b: &Number| {
+        format!(
+            "({} {} {})",
+            op,
+            smtlib_number(zebra, a),
+            smtlib_number(zebra, b)
+        )
+    };
+    match n {
+        Number::Const(c) => constant(*c),
+        Number::Value(p, o) if has_own_values(zebra, *p) => {
+            // the value of the last choice unless one of the others
+            let last = props.get_choice_count(*p) - 1;
+            let mut result = constant(props.get_value(*p, last));
+            for ch in (0..last).rev() {
+                result = format!(
+                    "(ite (= {} {}) {} {})",
+                    variable(*p, *o),
+                    ch,
+                    constant(props.get_value(*p, ch)),
+                    result
+                );
+            }
+            result
+        }
+        Number::Choice(p, o) | Number::Value(p, o) => variable(*p, *o),
+        Number::Add(a, b) => pair("+", a, b),
+        Number::Sub(a, b) => pair("-", a, b),
+        Number::Mul(a, b) => pair("*", a, b),
+        Number::Abs(a) => format!("(abs {})", smtlib_number(zebra, a)),
+        Number::Mod(a, n) => format!("(mod {} {})", smtlib_number(zebra, a), n),
+    }
+}
+
+fn smtlib_comparison(a: String, comparison: Comparison, b: String) -> String {
+    let constant = |c: i64| match c {
+        c if c < 0 => format!("(- {})", -c),
+        c => c.to_string(),
+    };
+    match comparison {
+        Comparison::Less => format!("(< {} {})", a, b),
+        Comparison::LessOrEqual => format!("(<= {} {})", a, b),
+        Comparison::Greater => format!("(> {} {})", a, b),
+        Comparison::GreaterOrEqual => format!("(>= {} {})", a, b),
+        Comparison::Equal => format!("(= {} {})", a, b),
+        Comparison::NotEqual => format!("(distinct {} {})", a, b),
+        Comparison::Difference(k) => format!("(= (- {} {}) {})", a, b, constant(k)),
+        Comparison::AbsDifference(k) => format!("(= (abs (- {} {})) {})", a, b, constant(k)),
+    }
+}
+
+fn smtlib_formula(zebra: &Zebra, f: &Formula) -> String {
+    let props = zebra.get_properties();
+    let object_count = props.get_object_count();
+    let variable = |p: usize, o: usize| format!("{}_{}", identifier(props.get_property_name(p)), o);
+    let join = |items: &[Formula], op: &str| {
+        let items: Vec<String> = items.iter().map(|f| smtlib_formula(zebra, f)).collect();
+        format!("({} {})", op, items.join(" "))
+    };
+    match f {
+        Formula::And(items) if items.is_empty() => "true".to_string(),
+        Formula::Or(items) if items.is_empty() => "false".to_string(),
+        Formula::Is(p, o, ch) => format!("(= {} {})", variable(*p, *o), ch),
+        Formula::Not(a) => format!("(not {})", smtlib_formula(zebra, a)),
+        Formula::And(items) => join(items, "and"),
+        Formula::Or(items) => join(items, "or"),
+        Formula::Implies(a, b) => format!(
+            "(=> {} {})",
+            smtlib_formula(zebra, a),
+            smtlib_formula(zebra, b)
+        ),
+        Formula::AllDifferent(p) => {
+            let variables: Vec<String> = (0..object_count).map(|o| variable(*p, o)).collect();
+            match object_count {
+                1 => "true".to_string(),
+                _ => format!("(distinct {})", variables.join(" ")),
+            }
+        }
+        Formula::Count(items, min, max) => {
+            let terms: Vec<String> = items
+                .iter()
+                .map(|f| format!("(ite {} 1 0)", smtlib_formula(zebra, f)))
+                .collect();
+            let sum = match terms.len() {
+                0 => "0".to_string(),
+                1 => terms[0].clone(),
+                _ => format!("(+ {})", terms.join(" ")),
+            };
+            if min == max {
+                format!("(= {} {})", sum, min)
+            } else if *max >= items.len() {
+                format!("(<= {} {})", min, sum)
+            } else {
+                format!("(and (<= {} {}) (<= {} {}))", min, sum, sum, max)
+            }
+        }
+        Formula::Compare(a, comparison, b) => smtlib_comparison(
+            smtlib_number(zebra, a),
+            *comparison,
+            smtlib_number(zebra, b),
+        ),
+    }
+}
+
+/// Writes the puzzle from the state as an SMT-LIB 2 script over integers,
+/// with the same variables and constraints as `to_minizinc`. The logic is
+/// QF_LIA unless an arithmetic clue multiplies values.
+pub fn to_smtlib(zebra: &Zebra) -> String {
+    let props = zebra.get_properties();
+    let model = model(zebra);
+    let mut result = String::new();
+    for line in legend(zebra) {
+        writeln!(result, "; {}", line).unwrap();
+    }
+    let linear = model
+        .groups
+        .iter()
+        .all(|(_, formulas)| formulas.iter().all(is_linear_formula));
+    let logic = if linear { "QF_LIA" } else { "QF_NIA" };
+    writeln!(result, "(set-logic {})", logic).unwrap();
+    for p in 0..props.get_property_count() {
+        let name = identifier(props.get_property_name(p));
+        for o in 0..props.get_object_count() {
+            writeln!(result, "(declare-const {}_{} Int)", name, o).unwrap();
+            writeln!(
+                result,
+                "(assert (and (<= 0 {}_{}) (< {}_{} {})))",
+                name,
+                o,
+                name,
+                o,
+                props.get_choice_count(p)
+            )
+            .unwrap();
+        }
+    }
+    for (comment, formulas) in &model.groups {
+        writeln!(result, "; {}", comment).unwrap();
+        for f in formulas {
+            writeln!(result, "(assert {})", smtlib_formula(zebra, f)).unwrap();
+        }
+    }
+    for text in &model.skipped {
+        writeln!(result, "; not exported: {}", text).unwrap();
+    }
+    writeln!(result, "(check-sat)").unwrap();
+    writeln!(result, "(get-model)").unwrap();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::backtracking::find_all;
+    use crate::expression::{Expr, Selector};
+    use crate::grounding::tuples;
+    use crate::literal::Literal;
+    use crate::relation::{Arrangement, GridRelation, Relation};
+    use crate::zebra::ZebraBuilder;
+
+    fn init_zebra<'a>() -> Zebra<'a> {
+        ZebraBuilder::new()
+            .set_object_count(2)
+            .choice("name", vec!["Ann", "Bob"])
+            .choice("place", vec!["1", "2"])
+            .choice("pet", vec!["cat", "dog"])
+            .fact("name", "Ann", "pet", "cat")
+            .relation(
+                Relation::LeftOf,
+                "place",
+                vec![("pet", "dog"), ("pet", "cat")],
+            )
+            .either(vec![
+                Literal::fact("name", "Bob", "pet", "dog"),
+                Literal::fact("place", "1", "pet", "dog"),
+            ])
+            .build()
+    }
+
+    #[test]
+    fn test_to_minizinc() {
+        let text = to_minizinc(&init_zebra());
+        assert!(text.contains("array[0..1] of var 0..1: pet;\n"));
+        assert!(text.contains("constraint name[0] != 1;\n"));
+        assert!(text.contains("constraint alldifferent(place);\n"));
+        assert!(text.contains("constraint (name[1] = 0 -> pet[1] = 0);\n"));
+        assert!(text.contains("constraint (pet[0] = 1 -> (pet[1] = 0 /\\ place[0] < place[1]));\n"));
+        assert!(text.contains("in 1..1;\n"));
+        assert!(!text.contains("not exported"));
+        assert!(text.ends_with(
+            "output [\"name = \\(name)\\n\", \"place = \\(place)\\n\", \"pet = \\(pet)\\n\"];\n"
+        ));
+    }
+
+    #[test]
+    fn test_to_smtlib() {
+        let text = to_smtlib(&init_zebra());
+        assert!(text.contains("(declare-const pet_1 Int)\n"));
+        assert!(text.contains("(assert (and (<= 0 pet_1) (< pet_1 2)))\n"));
+        assert!(text.contains("(assert (distinct place_0 place_1))\n"));
+        assert!(text.contains("(assert (=> (= name_1 0) (= pet_1 0)))\n"));
+        assert!(text.contains("(assert (=> (= pet_0 1) (and (= pet_1 0) (< place_0 place_1))))\n"));
+        assert!(text.contains("(set-logic QF_LIA)\n"));
+        assert!(text.ends_with("(check-sat)\n(get-model)\n"));
+    }
+
+    fn init_arithmetic_zebra<'a>() -> Zebra<'a> {
+        ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("name", vec!["Ann", "Bob", "Cid"])
+            .numeric("age", vec!["20", "30", "40"])
+            .choice("seat", vec!["1", "2", "3"])
+            .arrangement("seat", Arrangement::Circle)
+            .relation(
+                Relation::LeftOf,
+                "seat",
+                vec![("name", "Ann"), ("name", "Bob")],
+            )
+            .compare(
+                "age",
+                Comparison::Difference(-10),
+                ("name", "Bob"),
+                ("name", "Cid"),
+            )
+            .arithmetic(
+                Expr::value("age", Selector::has("name", "Cid").offset("seat", 1))
+                    * Expr::value("age", Selector::has("name", "Ann")),
+                Comparison::Less,
+                Expr::Const(1000),
+            )
+            .build()
+    }
+
+    #[test]
+    fn test_export_arithmetic() {
+        let zebra = init_arithmetic_zebra();
+        let text = to_minizinc(&zebra);
+        assert!(text.contains("array[0..2] of int: age_value = array1d(0..2, [20, 30, 40]);\n"));
+        assert!(text.contains("(((seat[1] - seat[0]) + 3) mod 3) = 1"));
+        assert!(text.contains("seat[1] = ((seat[0] + 1) mod 3)"));
+        assert!(text.contains("age_value[age[2]] - age_value[age[1]] = 10"));
+        assert!(text.contains("(age_value[age[1]] * age_value[age[0]]) < 1000"));
+        assert!(!text.contains("not exported"));
+        let text = to_smtlib(&zebra);
+        assert!(text.contains("(set-logic QF_NIA)\n"));
+        assert!(text.contains("(= (mod (+ (- seat_1 seat_0) 3) 3) 1)"));
+        assert!(text.contains("(ite (= age_1 0) 20 (ite (= age_1 1) 30 40))"));
+    }
+
+    // Every way to give the objects their choices, the identity being fixed.
+    fn assignments(zebra: &Zebra) -> Vec<Vec<Vec<usize>>> {
+        let props = zebra.get_properties();
+        let object_count = props.get_object_count();
+        let mut result = vec![vec![]];
+        for p in 0..props.get_property_count() {
+            let rows = match p == props.get_identity() {
+                true => vec![(0..object_count).collect()],
+                false => tuples(props.get_choice_count(p), object_count),
+            };
+            result = result
+                .into_iter()
+                .flat_map(|a: Vec<Vec<usize>>| {
+                    rows.iter().map(move |row| {
+                        let mut a = a.clone();
+                        a.push(row.clone());
+                        a
+                    })
+                })
+                .collect();
+        }
+        result
+    }
+
+    // The exported constraints hold exactly for the solutions.
+    fn check_model(zebra: &Zebra) {
+        let model = model(zebra);
+        assert!(model.skipped.is_empty());
+        let props = zebra.get_properties();
+        let holds = |choice: &dyn Fn(usize, usize) -> usize| {
+            model
+                .groups
+                .iter()
+                .all(|(_, formulas)| formulas.iter().all(|f| f.evaluate(&props, choice)))
+        };
+        let solutions = find_all(zebra.clone());
+        for s in &solutions {
+            assert!(holds(&|p, o| s.get_determined(p, o).unwrap()), "{}", s);
+        }
+        let count = assignments(zebra)
+            .iter()
+            .filter(|a| holds(&|p, o| a[p][o]))
+            .count();
+        assert_eq!(solutions.len(), count);
+    }
+
+    #[test]
+    fn test_model_solutions() {
+        check_model(&init_zebra());
+        check_model(&init_arithmetic_zebra());
+        let zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("name", vec!["Ann", "Bob", "Cid"])
+            .numeric("age", vec!["20", "30", "40"])
+            .choice("pet", vec!["cat", "dog", "fox"])
+            .choice("place", vec!["1", "2", "3"])
+            .occurrence("pet", vec!["cat", "fox"], 0, 2)
+            .fact("pet", "fox", "age", "40")
+            .predicate(
+                "name",
+                "Bob",
+                "pet",
+                "cat",
+                "age",
+                "age",
+                Box::new(|a, b| a < b),
+            )
+            .implies(
+                Literal::not_fact("name", "Bob", "place", "1"),
+                Literal::relation(
+                    Relation::Between,
+                    "place",
+                    vec![("age", "30"), ("pet", "fox"), ("name", "Ann")],
+                ),
+            )
+            .at_most(
+                1,
+                vec![
+                    Literal::fact("pet", "cat", "age", "20"),
+                    Literal::relation(Relation::AtEnd, "place", vec![("name", "Bob")]),
+                ],
+            )
+            .object_cardinality(vec![("pet", vec!["cat", "fox"])], 1, 2)
+            .build();
+        check_model(&zebra);
+        let zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("name", vec!["Ann", "Bob", "Cid"])
+            .grid("floor", vec!["1", "2"], "side", vec!["left", "right"])
+            .occurrence("floor", vec!["1", "2"], 0, 2)
+            .occurrence("side", vec!["left", "right"], 0, 2)
+            .grid_relation(
+                GridRelation::DirectlyAbove,
+                "floor",
+                "side",
+                ("name", "Ann"),
+                ("name", "Bob"),
+            )
+            .build();
+        check_model(&zebra);
+    }
+
+    #[test]
+    fn test_identifier() {
+        assert_eq!("color", identifier("color"));
+        assert_eq!("house_number", identifier("house number"));
+        assert_eq!("p_1st", identifier("1st"));
+    }
+}
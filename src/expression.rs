@@ -0,0 +1,304 @@
+use crate::relation::{Arrangement, Comparison};
+use crate::zebra::Zebra;
+use std::fmt::{self, Display};
+use std::ops::{Add, Mul, Sub};
+
+/// Selects a single object of the puzzle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selector<'a> {
+    /// The object having the choice of the property.
+    Has(&'a str, &'a str),
+    /// The object `offset` places away from the selected one in the ordered
+    /// property, -1 being the left neighbour. Wraps around on a circle.
+    Offset(Box<Selector<'a>>, &'a str, i64),
+}
+
+/// Integer expression over the values of objects, see
+/// `ZebraBuilder::arithmetic`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr<'a> {
+    Const(i64),
+    /// The value of the property of the selected object.
+    Value(&'a str, Selector<'a>),
+    Add(Box<Expr<'a>>, Box<Expr<'a>>),
+    Sub(Box<Expr<'a>>, Box<Expr<'a>>),
+    Mul(Box<Expr<'a>>, Box<Expr<'a>>),
+}
+
+impl<'a> Selector<'a> {
+    pub fn has(property: &'a str, choice: &'a str) -> Self {
+        Selector::Has(property, choice)
+    }
+
+    pub fn offset(self, property: &'a str, offset: i64) -> Self {
+        Selector::Offset(Box::new(self), property, offset)
+    }
+}
+
+impl<'a> Expr<'a> {
+    pub fn value(property: &'a str, selector: Selector<'a>) -> Self {
+        Expr::Value(property, selector)
+    }
+}
+
+impl<'a> Add for Expr<'a> {
+    type Output = Expr<'a>;
+    fn add(self, other: Expr<'a>) -> Expr<'a> {
+        Expr::Add(Box::new(self), Box::new(other))
+    }
+}
+
+impl<'a> Sub for Expr<'a> {
+    type Output = Expr<'a>;
+    fn sub(self, other: Expr<'a>) -> Expr<'a> {
+        Expr::Sub(Box::new(self), Box::new(other))
+    }
+}
+
+impl<'a> Mul for Expr<'a> {
+    type Output = Expr<'a>;
+    fn mul(self, other: Expr<'a>) -> Expr<'a> {
+        Expr::Mul(Box::new(self), Box::new(other))
+    }
+}
+
+impl Display for Selector<'_> {
+    fn fmt(&self, dest: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Selector::Has(p, ch) => write!(dest, "{}={}", p, ch),
+            Selector::Offset(s, p, offset) => write!(dest, "{}{:+} on {}", s, offset, p),
+        }
+    }
+}
+
+impl Expr<'_> {
+    // Writes the expression as an operand binding tighter than a sum, in
+    // parentheses when it is a sum or a difference.
+    fn fmt_operand(&self, dest: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Add(..) | Expr::Sub(..) => write!(dest, "({})", self),
+            _ => write!(dest, "{}", self),
+        }
+    }
+}
+
+impl Display for Expr<'_> {
+    fn fmt(&self, dest: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Const(c) => write!(dest, "{}", c),
+            Expr::Value(p, s) => write!(dest, "{}({})", p, s),
+            Expr::Add(a, b) => write!(dest, "{} + {}", a, b),
+            Expr::Sub(a, b) => {
+                write!(dest, "{} - ", a)?;
+                b.fmt_operand(dest)
+            }
+            Expr::Mul(a, b) => {
+                a.fmt_operand(dest)?;
+                write!(dest, " * ")?;
+                b.fmt_operand(dest)
+            }
+        }
+    }
+}
+
+/// `Selector` resolved to property and choice numbers.
+#[derive(Debug, Clone)]
+pub(crate) enum Target {
+    Has((usize, usize)),
+    Offset(Box<Target>, usize, i64),
+}
+
+/// `Expr` resolved to property and choice numbers.
+#[derive(Debug, Clone)]
+pub(crate) enum Term {
+    Const(i64),
+    Value(usize, Target),
+    Add(Box<Term>, Box<Term>),
+    Sub(Box<Term>, Box<Term>),
+    Mul(Box<Term>, Box<Term>),
+}
+
+#[derive(Debug)]
+pub(crate) struct ArithmeticClue {
+    pub(crate) lhs: Term,
+    pub(crate) comparison: Comparison,
+    pub(crate) rhs: Term,
+    pub(crate) clue: usize,
+}
+
+// Objects the target may select.
+fn candidates(zebra: &Zebra, target: &Target) -> Vec<usize> {
+    let object_count = zebra.get_properties().get_object_count();
+    match target {
+        Target::Has((p, ch)) => (0..object_count)
+            .filter(|o| zebra.is_choice_enabled(*p, *o, *ch))
+            .collect(),
+        Target::Offset(inner, property, offset) => {
+            let circle = zebra.get_properties().get_arrangement(*property) == Arrangement::Circle;
+            let place_count = zebra.get_properties().get_choice_count(*property);
+            let mut result = vec![];
+            for o in candidates(zebra, inner) {
+                for place in 0..place_count {
+                    let mut other = place as i64 + offset;
+                    if circle {
+                        other = other.rem_euclid(place_count as i64);
+                    }
+                    if !zebra.is_choice_enabled(*property, o, place)
+                        || other < 0
+                        || other >= place_count as i64
+                    {
+                        continue;
+                    }
+                    for o2 in 0..object_count {
+                        if o2 != o
+                            && !result.contains(&o2)
+                            && zebra.is_choice_enabled(*property, o2, other as usize)
+                        {
+                            result.push(o2);
+                        }
+                    }
+                }
+            }
+            result
+        }
+    }
+}
+
+// Interval containing every possible value of the term, `None` when the term
+// cannot be evaluated at all.
+fn evaluate(zebra: &Zebra, term: &Term) -> Option<(i64, i64)> {
+    let pair = |a: &Term, b: &Term| Some((evaluate(zebra, a)?, evaluate(zebra, b)?));
+    match term {
+        Term::Const(c) => Some((*c, *c)),
+        Term::Value(property, target) => {
+            let choice_count = zebra.get_properties().get_choice_count(*property);
+            let mut result: Option<(i64, i64)> = None;
+            for o in candidates(zebra, target) {
+                for v in 0..choice_count {
+                    if zebra.is_choice_enabled(*property, o, v) {
+                        let value = zebra.get_properties().get_value(*property, v);
+                        result = Some(match result {
+                            None => (value, value),
+                            Some((low, high)) => (low.min(value), high.max(value)),
+                        });
+                    }
+                }
+            }
+            result
+        }
+        Term::Add(a, b) => pair(a, b).map(|((a1, a2), (b1, b2))| (a1 + b1, a2 + b2)),
+        Term::Sub(a, b) => pair(a, b).map(|((a1, a2), (b1, b2))| (a1 - b2, a2 - b1)),
+        Term::Mul(a, b) => pair(a, b).map(|((a1, a2), (b1, b2))| {
+            let products = [a1 * b1, a1 * b2, a2 * b1, a2 * b2];
+            (
+                *products.iter().min().unwrap(),
+                *products.iter().max().unwrap(),
+            )
+        }),
+    }
+}
+
+fn is_feasible(zebra: &Zebra, c: &ArithmeticClue) -> bool {
+    match (evaluate(zebra, &c.lhs), evaluate(zebra, &c.rhs)) {
+        (Some((l1, l2)), Some((r1, r2))) => {
+            if l1 == l2 && r1 == r2 {
+                return c.comparison.holds(l1, r1);
+            }
+            let (min, max) = c.comparison.bounds(r1, r2);
+            l1.max(min) <= l2.min(max)
+        }
+        _ => false,
+    }
+}
+
+fn collect_properties(term: &Term, result: &mut Vec<usize>) {
+    let mut add = |p: usize| {
+        if !result.contains(&p) {
+            result.push(p);
+        }
+    };
+    fn target_properties(target: &Target, add: &mut dyn FnMut(usize)) {
+        match target {
+            Target::Has((p, _)) => add(*p),
+            Target::Offset(inner, p, _) => {
+                add(*p);
+                target_properties(inner, add);
+            }
+        }
+    }
+    match term {
+        Term::Const(_) => {}
+        Term::Value(p, target) => {
+            add(*p);
+            target_properties(target, &mut add);
+        }
+        Term::Add(a, b) | Term::Sub(a, b) | Term::Mul(a, b) => {
+            collect_properties(a, result);
+            collect_properties(b, result);
+        }
+    }
+}
+
+/// Removes every choice which, when assumed, makes the intervals of both
+/// sides incompatible. Returns false when the clue cannot hold.
+pub(crate) fn apply_arithmetic(
+    zebra: &mut Zebra,
+    c: &ArithmeticClue,
+    change_counter: &mut i32,
+) -> bool {
+    if !is_feasible(zebra, c) {
+        return false;
+    }
+    let object_count = zebra.get_properties().get_object_count();
+    let mut properties = vec![];
+    collect_properties(&c.lhs, &mut properties);
+    collect_properties(&c.rhs, &mut properties);
+    for property in properties {
+        let choice_count = zebra.get_properties().get_choice_count(property);
+        for object in 0..object_count {
+            if zebra.get_determined(property, object).is_some() {
+                continue;
+            }
+            for choice in 0..choice_count {
+                if !zebra.is_choice_enabled(property, object, choice) {
+                    continue;
+                }
+                let mut z = zebra.clone();
+                z.determine_choice(property, object, choice);
+                let mut probe_counter = 0;
+                if !z.apply_permutations(&mut probe_counter) || !is_feasible(&z, c) {
+                    zebra.set_choice_enabled(property, object, choice, false);
+                    *change_counter += 1;
+                }
+            }
+            if !(0..choice_count).any(|ch| zebra.is_choice_enabled(property, object, ch)) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_expression_display() {
+        let x = || Expr::value("age", Selector::has("pet", "cat"));
+        let y = || Expr::value("age", Selector::has("pet", "dog"));
+        assert_eq!(
+            "(age(pet=cat) + 1) * age(pet=dog)",
+            ((x() + Expr::Const(1)) * y()).to_string()
+        );
+        assert_eq!(
+            "age(pet=cat) - (age(pet=dog) - 1) * 2",
+            (x() - (y() - Expr::Const(1)) * Expr::Const(2)).to_string()
+        );
+        assert_eq!(
+            "age(pet=cat) - (age(pet=dog) + 1)",
+            (x() - (y() + Expr::Const(1))).to_string()
+        );
+    }
+}
@@ -0,0 +1,871 @@
+use crate::expression::{Target, Term};
+use crate::relation::{Arrangement, Comparison, GridRelation, Relation};
+use crate::zebra::{ClueKind, Condition, Counted, Fact, RelationClue, Zebra, ZebraProperties};
+
+/// Integer expression over the choices of the objects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Number {
+    Const(i64),
+    /// The number of the choice of the object, its place in an ordered
+    /// property: property and object.
+    Choice(usize, usize),
+    /// The value of the choice of the object, see `ZebraProperties::get_value`.
+    Value(usize, usize),
+    Add(Box<Number>, Box<Number>),
+    Sub(Box<Number>, Box<Number>),
+    Mul(Box<Number>, Box<Number>),
+    Abs(Box<Number>),
+    /// Remainder of a number, never negative, divided by the constant.
+    Mod(Box<Number>, i64),
+}
+
+/// Constraint over the choices of the objects. Every clue of the puzzle is
+/// a list of formulas, shared by the CNF encoding and the export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Formula {
+    /// The object has the choice: property, object and choice.
+    Is(usize, usize, usize),
+    Not(Box<Formula>),
+    /// `And(vec![])` is true.
+    And(Vec<Formula>),
+    /// `Or(vec![])` is false.
+    Or(Vec<Formula>),
+    Implies(Box<Formula>, Box<Formula>),
+    /// The objects have different choices of the property.
+    AllDifferent(usize),
+    /// Between the lowest and the highest number of the formulas hold.
+    Count(Vec<Formula>, usize, usize),
+    Compare(Number, Comparison, Number),
+}
+
+pub(crate) const TRUE: Formula = Formula::And(vec![]);
+pub(crate) const FALSE: Formula = Formula::Or(vec![]);
+
+pub(crate) fn not(a: Formula) -> Formula {
+    match a {
+        a if a == TRUE => FALSE,
+        a if a == FALSE => TRUE,
+        Formula::Not(a) => *a,
+        a => Formula::Not(Box::new(a)),
+    }
+}
+
+pub(crate) fn and(items: Vec<Formula>) -> Formula {
+    let mut result: Vec<Formula> = vec![];
+    for item in items {
+        if item == FALSE {
+            return FALSE;
+        }
+        if let Formula::And(inner) = item {
+            match and(result.into_iter().chain(inner).collect()) {
+                Formula::And(merged) => result = merged,
+                merged if merged == FALSE => return FALSE,
+                merged => result = vec![merged],
+            }
+            continue;
+        }
+        if let Formula::Is(p, o, ch) = item {
+            // an object has a single choice of each property
+            if result.iter().any(
+                |f| matches!(f, Formula::Is(p2, o2, ch2) if (*p2, *o2) == (p, o) && *ch2 != ch),
+            ) {
+                return FALSE;
+            }
+        }
+        if item != TRUE && !result.contains(&item) {
+            result.push(item);
+        }
+    }
+    match result.len() {
+        1 => result.pop().unwrap(),
+        _ => Formula::And(result),
+    }
+}
+
+pub(crate) fn or(items: Vec<Formula>) -> Formula {
+    let mut result: Vec<Formula> = vec![];
+    for item in items {
+        if item == TRUE {
+            return TRUE;
+        }
+        if let Formula::Or(inner) = item {
+            result.extend(
+                inner
+                    .into_iter()
+                    .filter(|f| !result.contains(f))
+                    .collect::<Vec<_>>(),
+            );
+            continue;
+        }
+        if item != FALSE && !result.contains(&item) {
+            result.push(item);
+        }
+    }
+    match result.len() {
+        1 => result.pop().unwrap(),
+        _ => Formula::Or(result),
+    }
+}
+
+pub(crate) fn implies(a: Formula, b: Formula) -> Formula {
+    if a == FALSE || b == TRUE {
+        TRUE
+    } else if a == TRUE {
+        b
+    } else if b == FALSE {
+        not(a)
+    } else {
+        Formula::Implies(Box::new(a), Box::new(b))
+    }
+}
+
+fn compare(a: Number, comparison: Comparison, b: Number) -> Formula {
+    match a == b {
+        // any number compares with itself as zero does
+        true if comparison.holds(0, 0) => TRUE,
+        true => FALSE,
+        false => Formula::Compare(a, comparison, b),
+    }
+}
+
+fn add(a: Number, b: Number) -> Number {
+    Number::Add(Box::new(a), Box::new(b))
+}
+
+fn sub(a: Number, b: Number) -> Number {
+    Number::Sub(Box::new(a), Box::new(b))
+}
+
+impl Number {
+    /// The value given the choice of each property of each object.
+    pub(crate) fn evaluate(
+        &self,
+        props: &ZebraProperties,
+        choice: &dyn Fn(usize, usize) -> usize,
+    ) -> i64 {
+        let evaluate = |n: &Number| n.evaluate(props, choice);
+        match self {
+            Number::Const(c) => *c,
+            Number::Choice(p, o) => choice(*p, *o) as i64,
+            Number::Value(p, o) => props.get_value(*p, choice(*p, *o)),
+            Number::Add(a, b) => evaluate(a) + evaluate(b),
+            Number::Sub(a, b) => evaluate(a) - evaluate(b),
+            Number::Mul(a, b) => evaluate(a) * evaluate(b),
+            Number::Abs(a) => evaluate(a).abs(),
+            Number::Mod(a, n) => evaluate(a).rem_euclid(*n),
+        }
+    }
+
+    /// Adds the (property, object) pairs whose choices the number depends on.
+    pub(crate) fn collect_variables(&self, result: &mut Vec<(usize, usize)>) {
+        match self {
+            Number::Const(_) => {}
+            Number::Choice(p, o) | Number::Value(p, o) => {
+                if !result.contains(&(*p, *o)) {
+                    result.push((*p, *o));
+                }
+            }
+            Number::Add(a, b) | Number::Sub(a, b) | Number::Mul(a, b) => {
+                a.collect_variables(result);
+                b.collect_variables(result);
+            }
+            Number::Abs(a) | Number::Mod(a, _) => a.collect_variables(result),
+        }
+    }
+}
+
+impl Formula {
+    /// Whether the formula holds given the choice of each property of each
+    /// object.
+    #[cfg(test)]
+    pub(crate) fn evaluate(
+        &self,
+        props: &ZebraProperties,
+        choice: &dyn Fn(usize, usize) -> usize,
+    ) -> bool {
+        let evaluate = |f: &Formula| f.evaluate(props, choice);
+        match self {
+            Formula::Is(p, o, ch) => choice(*p, *o) == *ch,
+            Formula::Not(a) => !evaluate(a),
+            Formula::And(items) => items.iter().all(evaluate),
+            Formula::Or(items) => items.iter().any(evaluate),
+            Formula::Implies(a, b) => !evaluate(a) || evaluate(b),
+            Formula::AllDifferent(p) => {
+                let object_count = props.get_object_count();
+                (0..object_count)
+                    .all(|o1| (o1 + 1..object_count).all(|o2| choice(*p, o1) != choice(*p, o2)))
+            }
+            Formula::Count(items, min, max) => {
+                let count = items.iter().filter(|f| evaluate(f)).count();
+                *min <= count && count <= *max
+            }
+            Formula::Compare(a, comparison, b) => {
+                comparison.holds(a.evaluate(props, choice), b.evaluate(props, choice))
+            }
+        }
+    }
+}
+
+// Every list of `length` numbers below `count`.
+pub(crate) fn tuples(count: usize, length: usize) -> Vec<Vec<usize>> {
+    let mut result = vec![vec![]];
+    for _ in 0..length {
+        result = result
+            .into_iter()
+            .flat_map(|t| {
+                (0..count).map(move |i| {
+                    let mut t = t.clone();
+                    t.push(i);
+                    t
+                })
+            })
+            .collect();
+    }
+    result
+}
+
+fn is((property, choice): (usize, usize), object: usize) -> Formula {
+    Formula::Is(property, object, choice)
+}
+
+// The objects having the choice allowed to fewer objects have the other
+// choice too, as in `Zebra::implied_choices`.
+fn fact(props: &ZebraProperties, f: &Fact) -> Vec<Formula> {
+    let object_count = props.get_object_count();
+    let (_, max1) = props.get_occurrence(f.choice1.0, f.choice1.1);
+    let (_, max2) = props.get_occurrence(f.choice2.0, f.choice2.1);
+    let mut implications = vec![];
+    if max1 <= max2 {
+        implications.push((f.choice1, f.choice2));
+    }
+    if max2 <= max1 {
+        implications.push((f.choice2, f.choice1));
+    }
+    let mut result = vec![];
+    for (choice1, choice2) in implications {
+        for o in 0..object_count {
+            result.push(implies(is(choice1, o), is(choice2, o)));
+        }
+    }
+    result
+}
+
+// Some object has the choice.
+fn owned(object_count: usize, choice: (usize, usize)) -> Formula {
+    or((0..object_count).map(|o| is(choice, o)).collect())
+}
+
+/// The relation holds on the places, numbers between 0 and `count` - 1,
+/// laid out by `arrangement`. The same as `Relation::holds_in`.
+pub(crate) fn relation_formula(
+    relation: Relation,
+    places: &[Number],
+    count: usize,
+    arrangement: Arrangement,
+) -> Formula {
+    let n = count as i64;
+    let place = |i: usize| places[i].clone();
+    let is_const = |a: Number, c: i64| compare(a, Comparison::Equal, Number::Const(c));
+    if arrangement == Arrangement::Circle {
+        // seats from the first place to the second one going right
+        let diff = |a: usize, b: usize| {
+            Number::Mod(Box::new(add(sub(place(b), place(a)), Number::Const(n))), n)
+        };
+        return match relation {
+            Relation::LeftOf | Relation::ImmediatelyLeftOf => is_const(diff(0, 1), 1),
+            Relation::RightOf | Relation::ImmediatelyRightOf => is_const(diff(1, 0), 1),
+            Relation::NextTo => or(vec![is_const(diff(0, 1), 1), is_const(diff(1, 0), 1)]),
+            Relation::Distance(k) if 2 * k <= count => {
+                let k = k as i64;
+                or(vec![is_const(diff(0, 1), k), is_const(diff(0, 1), n - k)])
+            }
+            Relation::Between => or(vec![
+                and(vec![is_const(diff(1, 0), 1), is_const(diff(0, 2), 1)]),
+                and(vec![is_const(diff(2, 0), 1), is_const(diff(0, 1), 1)]),
+            ]),
+            Relation::Opposite if n % 2 == 0 => is_const(diff(0, 1), n / 2),
+            _ => FALSE,
+        };
+    }
+    // twice the place plus one, compared with the count to find the half
+    let double = |i: usize| {
+        add(
+            Number::Mul(Box::new(Number::Const(2)), Box::new(place(i))),
+            Number::Const(1),
+        )
+    };
+    let half = |i: usize, comparison: Comparison| compare(double(i), comparison, Number::Const(n));
+    match relation {
+        Relation::LeftOf => compare(place(0), Comparison::Less, place(1)),
+        Relation::RightOf => compare(place(0), Comparison::Greater, place(1)),
+        Relation::ImmediatelyLeftOf => compare(place(0), Comparison::Difference(-1), place(1)),
+        Relation::ImmediatelyRightOf => compare(place(0), Comparison::Difference(1), place(1)),
+        Relation::NextTo => compare(place(0), Comparison::AbsDifference(1), place(1)),
+        Relation::Distance(k) => compare(place(0), Comparison::AbsDifference(k as i64), place(1)),
+        Relation::Between => or(vec![
+            and(vec![
+                compare(place(1), Comparison::Less, place(0)),
+                compare(place(0), Comparison::Less, place(2)),
+            ]),
+            and(vec![
+                compare(place(2), Comparison::Less, place(0)),
+                compare(place(0), Comparison::Less, place(1)),
+            ]),
+        ]),
+        Relation::AtEnd => or(vec![is_const(place(0), 0), is_const(place(0), n - 1)]),
+        Relation::SameHalf => or(vec![
+            and(vec![half(0, Comparison::Less), half(1, Comparison::Less)]),
+            and(vec![
+                half(0, Comparison::Greater),
+                half(1, Comparison::Greater),
+            ]),
+        ]),
+        Relation::Opposite => FALSE,
+    }
+}
+
+// The relation (or its negation) holds on the places of the objects.
+fn relation_holds(props: &ZebraProperties, r: &RelationClue, objects: &[usize]) -> Formula {
+    let places: Vec<Number> = objects
+        .iter()
+        .map(|o| Number::Choice(r.property, *o))
+        .collect();
+    let count = props.get_choice_count(r.property);
+    let holds = relation_formula(
+        r.relation,
+        &places,
+        count,
+        props.get_arrangement(r.property),
+    );
+    if r.negated {
+        not(holds)
+    } else {
+        holds
+    }
+}
+
+// Some objects having the choices are in the relation, the object at
+// `fixed` among them.
+fn relation_support(
+    props: &ZebraProperties,
+    r: &RelationClue,
+    fixed: Option<(usize, usize)>,
+) -> Formula {
+    let length = r.choices.len();
+    let supports = tuples(props.get_object_count(), length)
+        .into_iter()
+        .filter(|objects| match fixed {
+            Some((i, object)) => objects[i] == object,
+            None => true,
+        })
+        .map(|objects| {
+            let choices: Vec<Formula> = (0..length).map(|i| is(r.choices[i], objects[i])).collect();
+            if and(choices.clone()) == FALSE {
+                return FALSE;
+            }
+            // the fixed object is known to have its choice
+            let mut items: Vec<Formula> = choices
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| !matches!(fixed, Some((j, _)) if j == *i))
+                .map(|(_, f)| f)
+                .collect();
+            items.push(relation_holds(props, r, &objects));
+            and(items)
+        })
+        .collect();
+    or(supports)
+}
+
+// Every object having one of the choices is in the relation with some
+// objects having the other ones.
+fn relation_clue(props: &ZebraProperties, r: &RelationClue) -> Vec<Formula> {
+    let mut result = vec![];
+    for (i, c) in r.choices.iter().enumerate() {
+        for o in 0..props.get_object_count() {
+            result.push(implies(is(*c, o), relation_support(props, r, Some((i, o)))));
+        }
+    }
+    result
+}
+
+fn condition(props: &ZebraProperties, condition: &Condition) -> Formula {
+    let object_count = props.get_object_count();
+    match condition {
+        Condition::Same(f) => {
+            let both = (0..object_count)
+                .map(|o| and(vec![is(f.choice1, o), is(f.choice2, o)]))
+                .collect();
+            let mut items = vec![or(both)];
+            items.extend(fact(props, f));
+            and(items)
+        }
+        Condition::Different(f) => not(self::condition(props, &Condition::Same(f.clone()))),
+        Condition::Relation(r) => {
+            let mut items = vec![relation_support(props, r, None)];
+            items.extend(relation_clue(props, r));
+            and(items)
+        }
+    }
+}
+
+// Every object having the first choice has a partner with the second one.
+fn supported(
+    object_count: usize,
+    choice1: (usize, usize),
+    choice2: (usize, usize),
+    holds: &dyn Fn(usize, usize) -> Formula,
+) -> Vec<Formula> {
+    (0..object_count)
+        .map(|o1| {
+            let partners = (0..object_count)
+                .map(|o2| and(vec![is(choice2, o2), holds(o1, o2)]))
+                .collect();
+            implies(is(choice1, o1), or(partners))
+        })
+        .collect()
+}
+
+fn grid_holds(
+    relation: GridRelation,
+    rows: usize,
+    columns: usize,
+    o1: usize,
+    o2: usize,
+) -> Formula {
+    let row = |o: usize| Number::Choice(rows, o);
+    let column = |o: usize| Number::Choice(columns, o);
+    let same_column = compare(column(o1), Comparison::Equal, column(o2));
+    match relation {
+        GridRelation::DirectlyAbove => and(vec![
+            compare(row(o1), Comparison::Difference(1), row(o2)),
+            same_column,
+        ]),
+        GridRelation::DirectlyBelow => and(vec![
+            compare(row(o1), Comparison::Difference(-1), row(o2)),
+            same_column,
+        ]),
+        GridRelation::SameRow => compare(row(o1), Comparison::Equal, row(o2)),
+        GridRelation::SameColumn => same_column,
+        GridRelation::Diagonal => and(vec![
+            compare(row(o1), Comparison::AbsDifference(1), row(o2)),
+            compare(column(o1), Comparison::AbsDifference(1), column(o2)),
+        ]),
+        GridRelation::Distance(k) => compare(
+            add(
+                Number::Abs(Box::new(sub(row(o1), row(o2)))),
+                Number::Abs(Box::new(sub(column(o1), column(o2)))),
+            ),
+            Comparison::Equal,
+            Number::Const(k as i64),
+        ),
+    }
+}
+
+// Whether every object selected by the term is given by a choice held by a
+// single object.
+fn has_unique_targets(props: &ZebraProperties, term: &Term) -> bool {
+    fn unique(props: &ZebraProperties, target: &Target) -> bool {
+        match target {
+            Target::Has((p, ch)) => props.get_occurrence(*p, *ch).1 <= 1,
+            Target::Offset(inner, _, _) => unique(props, inner),
+        }
+    }
+    match term {
+        Term::Const(_) => true,
+        Term::Value(_, target) => unique(props, target),
+        Term::Add(a, b) | Term::Sub(a, b) | Term::Mul(a, b) => {
+            has_unique_targets(props, a) && has_unique_targets(props, b)
+        }
+    }
+}
+
+// The objects the target may select, each with the formula selecting it.
+fn ground_target(props: &ZebraProperties, target: &Target) -> Vec<(Formula, usize)> {
+    let object_count = props.get_object_count();
+    match target {
+        Target::Has(c) => (0..object_count).map(|o| (is(*c, o), o)).collect(),
+        Target::Offset(inner, property, offset) => {
+            let place_count = props.get_choice_count(*property) as i64;
+            let place = |o: usize| Number::Choice(*property, o);
+            let mut result = vec![];
+            for (selected, o) in ground_target(props, inner) {
+                for o2 in (0..object_count).filter(|o2| *o2 != o) {
+                    let at_offset = match props.get_arrangement(*property) {
+                        Arrangement::Line => {
+                            compare(place(o2), Comparison::Difference(*offset), place(o))
+                        }
+                        Arrangement::Circle => {
+                            let moved =
+                                add(place(o), Number::Const(offset.rem_euclid(place_count)));
+                            compare(
+                                place(o2),
+                                Comparison::Equal,
+                                Number::Mod(Box::new(moved), place_count),
+                            )
+                        }
+                    };
+                    result.push((and(vec![selected.clone(), at_offset]), o2));
+                }
+            }
+            result
+        }
+    }
+}
+
+// The term over the values of the objects it may select, each with the
+// formula selecting them.
+fn ground_term(props: &ZebraProperties, term: &Term) -> Vec<(Formula, Number)> {
+    let pairs = |a: &Term, b: &Term, f: fn(Box<Number>, Box<Number>) -> Number| {
+        let mut result = vec![];
+        for (selected_a, a) in ground_term(props, a) {
+            for (selected_b, b) in ground_term(props, b) {
+                let selected = and(vec![selected_a.clone(), selected_b]);
+                if selected != FALSE {
+                    result.push((selected, f(Box::new(a.clone()), Box::new(b))));
+                }
+            }
+        }
+        result
+    };
+    match term {
+        Term::Const(c) => vec![(TRUE, Number::Const(*c))],
+        Term::Value(property, target) => ground_target(props, target)
+            .into_iter()
+            .map(|(selected, o)| (selected, Number::Value(*property, o)))
+            .collect(),
+        Term::Add(a, b) => pairs(a, b, Number::Add),
+        Term::Sub(a, b) => pairs(a, b, Number::Sub),
+        Term::Mul(a, b) => pairs(a, b, Number::Mul),
+    }
+}
+
+/// The clue as formulas holding together exactly when the clue holds in a
+/// solved state, as checked by `reference::check_clue`. `None` for
+/// arithmetic clues selecting objects by a shared choice, which have no
+/// exact value.
+pub(crate) fn ground_clue(zebra: &Zebra, clue: usize) -> Option<Vec<Formula>> {
+    let props = zebra.get_properties();
+    let object_count = props.get_object_count();
+    if zebra.get_clue_kind(clue) == ClueKind::Choice {
+        return Some(vec![]);
+    }
+    if let Some(f) = props.facts.iter().find(|f| f.clue == clue) {
+        return Some(fact(&props, f));
+    }
+    if let Some(f) = props.negative_facts.iter().find(|f| f.clue == clue) {
+        return Some(
+            (0..object_count)
+                .map(|o| not(and(vec![is(f.choice1, o), is(f.choice2, o)])))
+                .collect(),
+        );
+    }
+    if let Some(pred) = props.predicates.iter().find(|p| p.clue == clue) {
+        let mut allowed_pairs = vec![];
+        for ch3 in 0..props.get_choice_count(pred.property3) {
+            for ch4 in 0..props.get_choice_count(pred.property4) {
+                let name3 = props.get_property_choice_name_by_nr(pred.property3, ch3);
+                let name4 = props.get_property_choice_name_by_nr(pred.property4, ch4);
+                if (pred.test)(name3, name4) {
+                    allowed_pairs.push((ch3, ch4));
+                }
+            }
+        }
+        let mut result = vec![];
+        for objects in tuples(object_count, 2) {
+            let condition = and(vec![
+                is(pred.choice1, objects[0]),
+                is(pred.choice2, objects[1]),
+            ]);
+            let allowed = allowed_pairs
+                .iter()
+                .map(|&(ch3, ch4)| {
+                    and(vec![
+                        is((pred.property3, ch3), objects[0]),
+                        is((pred.property4, ch4), objects[1]),
+                    ])
+                })
+                .collect();
+            result.push(implies(condition, or(allowed)));
+        }
+        return Some(result);
+    }
+    if let Some(r) = props.relations.iter().find(|r| r.clue == clue) {
+        return Some(relation_clue(&props, r));
+    }
+    if let Some(d) = props.disjunctions.iter().find(|d| d.clue == clue) {
+        let conditions: Vec<Formula> = d.conditions.iter().map(|c| condition(&props, c)).collect();
+        let max = if d.exclusive { 1 } else { conditions.len() };
+        return Some(vec![Formula::Count(conditions, 1, max)]);
+    }
+    if let Some(i) = props.implications.iter().find(|i| i.clue == clue) {
+        return Some(vec![implies(
+            condition(&props, &i.condition),
+            condition(&props, &i.consequence),
+        )]);
+    }
+    if let Some(c) = props.cardinalities.iter().find(|c| c.clue == clue) {
+        let counted = match &c.counted {
+            Counted::Conditions(conditions) => {
+                conditions.iter().map(|c| condition(&props, c)).collect()
+            }
+            Counted::Objects(filter) => (0..object_count)
+                .map(|o| {
+                    and(filter
+                        .0
+                        .iter()
+                        .map(|(p, set)| or(set.iter().map(|ch| is((*p, *ch), o)).collect()))
+                        .collect())
+                })
+                .collect(),
+        };
+        return Some(vec![Formula::Count(counted, c.min, c.max)]);
+    }
+    if let Some(c) = props.comparisons.iter().find(|c| c.clue == clue) {
+        let holds = |o1: usize, o2: usize| {
+            compare(
+                Number::Value(c.property, o1),
+                c.comparison,
+                Number::Value(c.property, o2),
+            )
+        };
+        let mut result = vec![
+            owned(object_count, c.choice1),
+            owned(object_count, c.choice2),
+        ];
+        result.extend(supported(object_count, c.choice1, c.choice2, &holds));
+        result.extend(supported(object_count, c.choice2, c.choice1, &|o2, o1| {
+            holds(o1, o2)
+        }));
+        return Some(result);
+    }
+    if let Some(c) = props.arithmetic.iter().find(|c| c.clue == clue) {
+        if !has_unique_targets(&props, &c.lhs) || !has_unique_targets(&props, &c.rhs) {
+            return None;
+        }
+        let mut alternatives = vec![];
+        for (selected_l, l) in ground_term(&props, &c.lhs) {
+            for (selected_r, r) in ground_term(&props, &c.rhs) {
+                alternatives.push(and(vec![
+                    selected_l.clone(),
+                    selected_r,
+                    compare(l.clone(), c.comparison, r),
+                ]));
+            }
+        }
+        return Some(vec![or(alternatives)]);
+    }
+    if let Some(g) = props.grid_relations.iter().find(|g| g.clue == clue) {
+        let holds = |o1: usize, o2: usize| match o1 == o2 {
+            true => FALSE,
+            false => grid_holds(g.relation, g.rows, g.columns, o1, o2),
+        };
+        let mut result = supported(object_count, g.choice1, g.choice2, &holds);
+        result.extend(supported(object_count, g.choice2, g.choice1, &|o2, o1| {
+            holds(o1, o2)
+        }));
+        return Some(result);
+    }
+    Some(vec![])
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::expression::{Expr, Selector};
+    use crate::literal::Literal;
+    use crate::reference::is_clue_satisfied;
+    use crate::zebra::ZebraBuilder;
+
+    #[test]
+    fn test_relation_formula() {
+        let relations = [
+            Relation::LeftOf,
+            Relation::RightOf,
+            Relation::ImmediatelyLeftOf,
+            Relation::ImmediatelyRightOf,
+            Relation::NextTo,
+            Relation::Distance(0),
+            Relation::Distance(2),
+            Relation::Distance(3),
+            Relation::Between,
+            Relation::AtEnd,
+            Relation::SameHalf,
+            Relation::Opposite,
+        ];
+        let zebra = ZebraBuilder::new()
+            .set_object_count(1)
+            .choice("place", vec!["1"])
+            .build();
+        let props = zebra.get_properties();
+        for arrangement in [Arrangement::Line, Arrangement::Circle] {
+            for count in 1..=6 {
+                for relation in relations {
+                    let places: Vec<Number> = (0..relation.arity())
+                        .map(|i| Number::Choice(0, i))
+                        .collect();
+                    let f = relation_formula(relation, &places, count, arrangement);
+                    for t in tuples(count, relation.arity()) {
+                        assert_eq!(
+                            relation.holds_in(&t, count, arrangement),
+                            f.evaluate(&props, &|_, o| t[o]),
+                            "{:?} {:?} {:?}",
+                            relation,
+                            arrangement,
+                            t
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // Compares the formulas of every clue with the reference checker on all
+    // the ways to give the objects their choices, the names being fixed.
+    // The other properties have the same number of choices.
+    fn check_ground_clues(zebra: &Zebra) {
+        let props = zebra.get_properties();
+        let object_count = props.get_object_count();
+        let identity = props.get_identity();
+        let properties: Vec<usize> = (0..props.get_property_count())
+            .filter(|p| *p != identity)
+            .collect();
+        let choice_count = props.get_choice_count(properties[0]);
+        let clues: Vec<Vec<Formula>> = (0..zebra.get_clue_count())
+            .map(|c| ground_clue(zebra, c).unwrap())
+            .collect();
+        for choices in tuples(choice_count, properties.len() * object_count) {
+            let choice = |p: usize, o: usize| match properties.iter().position(|q| *q == p) {
+                Some(i) => choices[i * object_count + o],
+                None => o,
+            };
+            let mut solved = zebra.clone();
+            for &p in &properties {
+                for o in 0..object_count {
+                    solved.determine_choice(p, o, choice(p, o));
+                }
+            }
+            for (clue, formulas) in clues.iter().enumerate() {
+                assert_eq!(
+                    is_clue_satisfied(&solved, clue),
+                    formulas.iter().all(|f| f.evaluate(&props, &choice)),
+                    "{} with {:?}",
+                    zebra.get_clue_text(clue),
+                    choices
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_ground_clue() {
+        let zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("name", vec!["Ann", "Bob", "Cid"])
+            .numeric("age", vec!["20", "30", "40"])
+            .choice("pet", vec!["cat", "dog", "fox"])
+            .choice("place", vec!["1", "2", "3"])
+            .occurrence("pet", vec!["cat", "fox"], 0, 2)
+            .fact("pet", "fox", "age", "40")
+            .not_fact("pet", "cat", "place", "2")
+            .compare(
+                "age",
+                Comparison::Difference(10),
+                ("name", "Ann"),
+                ("pet", "dog"),
+            )
+            .predicate(
+                "name",
+                "Bob",
+                "pet",
+                "cat",
+                "age",
+                "age",
+                Box::new(|a, b| a < b),
+            )
+            .relation(
+                Relation::NextTo,
+                "place",
+                vec![("pet", "cat"), ("name", "Cid")],
+            )
+            .either(vec![
+                Literal::fact("name", "Ann", "pet", "fox"),
+                Literal::relation(Relation::AtEnd, "place", vec![("pet", "dog")]),
+            ])
+            .implies(
+                Literal::not_fact("name", "Bob", "place", "1"),
+                Literal::relation(
+                    Relation::Between,
+                    "place",
+                    vec![("age", "30"), ("pet", "fox"), ("name", "Ann")],
+                ),
+            )
+            .at_most(
+                1,
+                vec![
+                    Literal::fact("pet", "cat", "age", "20"),
+                    Literal::relation(
+                        Relation::LeftOf,
+                        "place",
+                        vec![("name", "Bob"), ("age", "30")],
+                    ),
+                ],
+            )
+            .object_cardinality(
+                vec![("pet", vec!["cat", "fox"]), ("age", vec!["20", "30"])],
+                1,
+                1,
+            )
+            .arithmetic(
+                Expr::value("age", Selector::has("name", "Ann").offset("place", 1)),
+                Comparison::Greater,
+                Expr::value("age", Selector::has("name", "Cid")) - Expr::Const(10),
+            )
+            .build();
+        check_ground_clues(&zebra);
+    }
+
+    #[test]
+    fn test_ground_clue_on_circle_and_grid() {
+        let zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("name", vec!["Ann", "Bob", "Cid"])
+            .choice("seat", vec!["1", "2", "3"])
+            .arrangement("seat", Arrangement::Circle)
+            .relation(
+                Relation::LeftOf,
+                "seat",
+                vec![("name", "Ann"), ("name", "Bob")],
+            )
+            .arithmetic(
+                Expr::value("seat", Selector::has("name", "Cid").offset("seat", -1)),
+                Comparison::Equal,
+                Expr::Const(2),
+            )
+            .build();
+        check_ground_clues(&zebra);
+        let zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("name", vec!["Ann", "Bob", "Cid"])
+            .grid("floor", vec!["1", "2"], "side", vec!["left", "right"])
+            .occurrence("floor", vec!["1", "2"], 0, 2)
+            .occurrence("side", vec!["left", "right"], 0, 2)
+            .grid_relation(
+                GridRelation::DirectlyAbove,
+                "floor",
+                "side",
+                ("name", "Ann"),
+                ("name", "Bob"),
+            )
+            .grid_relation(
+                GridRelation::Distance(2),
+                "floor",
+                "side",
+                ("name", "Bob"),
+                ("side", "left"),
+            )
+            .build();
+        check_ground_clues(&zebra);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::zebra::Zebra;
+
+/// Why a deduction can be made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    /// Follows from the clue with the given index.
+    Clue(usize),
+    /// Each choice belongs to a single object.
+    Permutation,
+}
+
+/// One step of reasoning: `object` has (or, when `holds` is false, cannot
+/// have) `choice` of `property`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deduction {
+    pub object: usize,
+    pub property: usize,
+    pub choice: usize,
+    pub holds: bool,
+    pub reason: Reason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hint {
+    Deduce(Deduction),
+    /// The state already contradicts a clue.
+    Contradiction(Reason),
+}
+
+/// Finds the next deduction which can be made without branching.
+///
+/// Hard clues are tried in order, then the permutation rule. Returns `None` when
+/// the propagation cannot make any progress and a guess would be needed.
+pub fn find_hint(zebra: &Zebra) -> Option<Hint> {
+    for clue in 0..zebra.get_clue_count() {
+        if !zebra.is_clue_enabled(clue) || zebra.get_clue_weight(clue).is_some() {
+            continue;
+        }
+        let mut z = zebra.clone();
+        let mut change_counter = 0;
+        if !z.apply_clue(clue, &mut change_counter) {
+            return Some(Hint::Contradiction(Reason::Clue(clue)));
+        }
+        if let Some(d) = find_change(zebra, &z, Reason::Clue(clue)) {
+            return Some(Hint::Deduce(d));
+        }
+    }
+
+    let mut z = zebra.clone();
+    let mut change_counter = 0;
+    if !z.apply_permutations(&mut change_counter) {
+        return Some(Hint::Contradiction(Reason::Permutation));
+    }
+    find_change(zebra, &z, Reason::Permutation).map(Hint::Deduce)
+}
+
+fn find_change(before: &Zebra, after: &Zebra, reason: Reason) -> Option<Deduction> {
+    let (object_count, property_count) = {
+        let props = before.get_properties();
+        (props.get_object_count(), props.get_property_count())
+    };
+    for property in 0..property_count {
+        for object in 0..object_count {
+            let choice_count = before.get_properties().get_choice_count(property);
+            let eliminated = (0..choice_count).find(|ch| {
+                before.is_choice_enabled(property, object, *ch)
+                    && !after.is_choice_enabled(property, object, *ch)
+            });
+            if let Some(choice) = eliminated {
+                // report the determined choice rather than the first removed one
+                let result = match after.get_determined(property, object) {
+                    Some(choice) => Deduction {
+                        object,
+                        property,
+                        choice,
+                        holds: true,
+                        reason,
+                    },
+                    None => Deduction {
+                        object,
+                        property,
+                        choice,
+                        holds: false,
+                        reason,
+                    },
+                };
+                return Some(result);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::zebra::ZebraBuilder;
+
+    fn make_zebra<'a>() -> Zebra<'a> {
+        ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("p1", vec!["p1a", "p1b", "p1c"])
+            .choice("p2", vec!["p2a", "p2b", "p2c"])
+            .fact("p1", "p1a", "p2", "p2b")
+            .not_fact("p1", "p1b", "p2", "p2c")
+            .build()
+    }
+
+    #[test]
+    fn test_hint_from_clue() {
+        let zebra = make_zebra();
+        let expected = Deduction {
+            object: 0,
+            property: 1,
+            choice: 1,
+            holds: true,
+            reason: Reason::Clue(2),
+        };
+        assert_eq!(Some(Hint::Deduce(expected)), find_hint(&zebra));
+    }
+
+    #[test]
+    fn test_hint_sequence() {
+        let mut zebra = make_zebra();
+        zebra.determine_choice(1, 0, 1);
+        let hint = find_hint(&zebra).unwrap();
+        let expected = Deduction {
+            object: 1,
+            property: 1,
+            choice: 2,
+            holds: false,
+            reason: Reason::Clue(3),
+        };
+        assert_eq!(Hint::Deduce(expected), hint);
+
+        // removing p2b leaves p2a as the only choice of object 1
+        zebra.set_choice_enabled(1, 1, 2, false);
+        let expected = Deduction {
+            object: 1,
+            property: 1,
+            choice: 0,
+            holds: true,
+            reason: Reason::Permutation,
+        };
+        assert_eq!(Some(Hint::Deduce(expected)), find_hint(&zebra));
+    }
+
+    #[test]
+    fn test_hint_contradiction() {
+        let mut zebra = make_zebra();
+        zebra.determine_choice(1, 0, 0);
+        assert_eq!(
+            Some(Hint::Contradiction(Reason::Clue(2))),
+            find_hint(&zebra)
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use crate::backtracking::{propagate, State, COUNTER};
+use std::fmt::Debug;
+
+/// A state whose branches are single decisions, which lets the search
+/// explain its failures by the decisions which caused them.
+pub trait Decide: State + Clone {
+    type Decision: Clone + PartialEq + Debug;
+    /// The decisions `branch` would make, one for each child. Together they
+    /// must cover all solutions of the state.
+    fn decisions(&self) -> Vec<Self::Decision>;
+    /// Every decision on the same variable as `decision`, including the ones
+    /// the state has already ruled out.
+    fn domain(&self, decision: &Self::Decision) -> Vec<Self::Decision>;
+    fn decide(&mut self, decision: &Self::Decision);
+    /// Whether the decision holds in every solution of the state.
+    fn entails(&self, decision: &Self::Decision) -> bool;
+}
+
+struct Learner<T: Decide> {
+    root: T,
+    // Sets of decisions which cannot hold together.
+    nogoods: Vec<Vec<T::Decision>>,
+}
+
+impl<T: Decide> Learner<T> {
+    fn violates_nogood(&self, s: &T) -> bool {
+        self.nogoods
+            .iter()
+            .any(|nogood| nogood.iter().all(|d| s.entails(d)))
+    }
+
+    // Whether the decisions taken on the root fail without any search.
+    fn fails(&self, decisions: &[T::Decision]) -> bool {
+        let mut s = self.root.clone();
+        for d in decisions {
+            s.decide(d);
+        }
+        !propagate(&mut s) || self.violates_nogood(&s)
+    }
+
+    // Shrinks the failing path to the decisions needed for the failure.
+    fn explain(&self, path: &[T::Decision]) -> Vec<T::Decision> {
+        let mut result = path.to_vec();
+        for i in (0..path.len()).rev() {
+            let mut smaller = result.clone();
+            smaller.retain(|d| *d != path[i]);
+            if self.fails(&smaller) {
+                result = smaller;
+            }
+        }
+        result
+    }
+
+    // Returns a solution or the decisions of the path which make the state
+    // fail. A child failing independently of its own decision makes its
+    // siblings fail too, so they are skipped.
+    fn search(&mut self, s: T, path: &mut Vec<T::Decision>) -> Result<T, Vec<T::Decision>> {
+        let mut s = s;
+        unsafe {
+            COUNTER += 1;
+        }
+
+        if !propagate(&mut s) || self.violates_nogood(&s) {
+            return Err(self.explain(path));
+        }
+        if s.is_solution() {
+            return Ok(s);
+        }
+
+        let decisions = s.decisions();
+        let mut conflict: Vec<T::Decision> = vec![];
+        for d in &decisions {
+            let mut child = s.clone();
+            child.decide(d);
+            path.push(d.clone());
+            let result = self.search(child, path);
+            path.pop();
+            match result {
+                Ok(solution) => return Ok(solution),
+                // backjump
+                Err(c) if !c.contains(d) => return Err(c),
+                Err(c) => {
+                    for d2 in c {
+                        if d2 != *d && !conflict.contains(&d2) {
+                            conflict.push(d2);
+                        }
+                    }
+                }
+            }
+        }
+        // The children only tried the decisions left by the path, the ones
+        // it ruled out need their own reasons.
+        if let Some(first) = decisions.first() {
+            for d in s.domain(first) {
+                if decisions.contains(&d) {
+                    continue;
+                }
+                path.push(d.clone());
+                let reason = if self.fails(path) {
+                    self.explain(path)
+                } else {
+                    path.clone()
+                };
+                path.pop();
+                for d2 in reason {
+                    if d2 != d && !conflict.contains(&d2) {
+                        conflict.push(d2);
+                    }
+                }
+            }
+        }
+        self.nogoods.push(conflict.clone());
+        Err(conflict)
+    }
+}
+
+/// Like `find_first`, but learns from every failure which decisions caused
+/// it. Those sets of decisions are never tried together again and the
+/// search jumps back over decisions which had nothing to do with the
+/// failure.
+pub fn find_first_learning<T: Decide>(s: T) -> Option<T> {
+    let mut learner = Learner {
+        root: s.clone(),
+        nogoods: vec![],
+    };
+    learner.search(s, &mut vec![]).ok()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::backtracking::find_first;
+    use crate::myzebra::init_my_zebra;
+    use crate::relation::Relation;
+    use crate::sudoku::Sudoku;
+    use crate::zebra::ZebraBuilder;
+
+    #[test]
+    fn test_find_first_learning() {
+        let zebra = init_my_zebra();
+        let expected = find_first(zebra.clone()).unwrap();
+        assert_eq!(
+            format!("{}", expected),
+            format!("{}", find_first_learning(zebra).unwrap())
+        );
+
+        let puzzle =
+            "8..........36......7..9.2...5...7.......457.....1...3...1....68..85...1..9....4..";
+        let sudoku = Sudoku::parse(puzzle).unwrap();
+        assert_eq!(
+            "812753649943682175675491283154237896369845721287169534521974368438526917796318452",
+            find_first_learning(sudoku).unwrap().to_line()
+        );
+    }
+
+    #[test]
+    fn test_find_first_learning_unsolvable() {
+        let zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("place", vec!["1", "2", "3"])
+            .choice("name", vec!["Ann", "Bob", "Cid"])
+            .relation(
+                Relation::LeftOf,
+                "place",
+                vec![("name", "Ann"), ("name", "Bob")],
+            )
+            .relation(
+                Relation::LeftOf,
+                "place",
+                vec![("name", "Bob"), ("name", "Cid")],
+            )
+            .relation(
+                Relation::LeftOf,
+                "place",
+                vec![("name", "Cid"), ("name", "Ann")],
+            )
+            .build();
+        assert!(find_first_learning(zebra).is_none());
+    }
+
+    #[test]
+    fn test_find_first_learning_pruned_choices() {
+        // propagation rules out choices the search then never branches on,
+        // the nogoods must account for them
+        let zebra = ZebraBuilder::new()
+            .set_object_count(5)
+            .choice("place", vec!["1", "2", "3", "4", "5"])
+            .choice("name", vec!["A", "B", "C", "D", "E"])
+            .choice("pet", vec!["cat", "dog", "fox", "owl", "ant"])
+            .choice("color", vec!["r", "g", "b", "w", "y"])
+            .relation(
+                Relation::LeftOf,
+                "place",
+                vec![("name", "E"), ("pet", "ant")],
+            )
+            .relation(
+                Relation::NextTo,
+                "place",
+                vec![("pet", "ant"), ("name", "B")],
+            )
+            .relation(
+                Relation::NextTo,
+                "place",
+                vec![("color", "y"), ("name", "C")],
+            )
+            .relation(
+                Relation::LeftOf,
+                "place",
+                vec![("color", "y"), ("name", "B")],
+            )
+            .relation(
+                Relation::NextTo,
+                "place",
+                vec![("pet", "ant"), ("name", "D")],
+            )
+            .relation(
+                Relation::NextTo,
+                "place",
+                vec![("color", "y"), ("name", "A")],
+            )
+            .build();
+        assert!(find_first(zebra.clone()).is_some());
+        assert!(find_first_learning(zebra).is_some());
+    }
+
+    // Random 5-object puzzles of facts and positional relations, solved with
+    // and without learning. A learned nogood removing a solution makes the
+    // learning search miss it.
+    #[test]
+    fn test_find_first_learning_random() {
+        let names = ["A", "B", "C", "D", "E"];
+        let pets = ["cat", "dog", "fox", "owl", "ant"];
+        let colors = ["r", "g", "b", "w", "y"];
+        let choices = [("name", names), ("pet", pets), ("color", colors)];
+        // xorshift64, good enough to vary the puzzles
+        let mut seed: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut below = |n: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % n as u64) as usize
+        };
+        for _ in 0..100 {
+            let mut builder = ZebraBuilder::new();
+            builder
+                .set_object_count(5)
+                .choice("place", vec!["1", "2", "3", "4", "5"])
+                .choice("name", names.to_vec())
+                .choice("pet", pets.to_vec())
+                .choice("color", colors.to_vec());
+            for _ in 0..4 + below(4) {
+                let (p1, c1) = choices[below(3)];
+                let (p2, c2) = choices[below(3)];
+                let (ch1, ch2) = (c1[below(5)], c2[below(5)]);
+                if p1 == p2 {
+                    continue;
+                }
+                match below(4) {
+                    0 => builder.fact(p1, ch1, p2, ch2),
+                    1 => builder.not_fact(p1, ch1, p2, ch2),
+                    2 => builder.relation(Relation::NextTo, "place", vec![(p1, ch1), (p2, ch2)]),
+                    _ => builder.relation(Relation::LeftOf, "place", vec![(p1, ch1), (p2, ch2)]),
+                };
+            }
+            let zebra = builder.build();
+            let clues: Vec<String> = (0..zebra.get_clue_count())
+                .map(|c| zebra.get_clue_text(c))
+                .collect();
+            assert_eq!(
+                find_first(zebra.clone()).is_some(),
+                find_first_learning(zebra).is_some(),
+                "{}",
+                clues.join("\n")
+            );
+        }
+    }
+}
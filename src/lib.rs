@@ -1,3 +1,20 @@
+pub mod analysis;
 pub mod backtracking;
+pub mod cdcl;
+pub mod cnf;
+pub mod diagnosis;
+pub mod export;
+pub mod expression;
+pub mod grounding;
+pub mod hint;
+pub mod learning;
+pub mod literal;
 pub mod myzebra;
+pub mod queens;
+pub mod reference;
+pub mod relation;
+pub mod soft;
+pub mod sudoku;
+pub mod suggestion;
+pub mod verification;
 pub mod zebra;
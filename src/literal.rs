@@ -0,0 +1,69 @@
+use crate::relation::Relation;
+use std::fmt::{self, Display};
+
+/// A simple statement about the objects having some choices, used as a part
+/// of compound clues. Choices are given as pairs of property and choice name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Literal<'a> {
+    /// The object having the first choice has the second one.
+    Fact((&'a str, &'a str), (&'a str, &'a str)),
+    /// The object having the first choice does not have the second one.
+    NotFact((&'a str, &'a str), (&'a str, &'a str)),
+    /// Objects having the choices are in the relation by their places in the
+    /// property.
+    Relation(Relation, &'a str, Vec<(&'a str, &'a str)>),
+}
+
+impl<'a> Literal<'a> {
+    pub fn fact(
+        property1: &'a str,
+        choice1: &'a str,
+        property2: &'a str,
+        choice2: &'a str,
+    ) -> Self {
+        Literal::Fact((property1, choice1), (property2, choice2))
+    }
+
+    pub fn not_fact(
+        property1: &'a str,
+        choice1: &'a str,
+        property2: &'a str,
+        choice2: &'a str,
+    ) -> Self {
+        Literal::NotFact((property1, choice1), (property2, choice2))
+    }
+
+    pub fn relation(
+        relation: Relation,
+        property: &'a str,
+        choices: Vec<(&'a str, &'a str)>,
+    ) -> Self {
+        Literal::Relation(relation, property, choices)
+    }
+}
+
+impl Display for Literal<'_> {
+    fn fmt(&self, dest: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Literal::Fact((p1, ch1), (p2, ch2)) => {
+                write!(dest, "fact: {}={}, {}={}", p1, ch1, p2, ch2)
+            }
+            Literal::NotFact((p1, ch1), (p2, ch2)) => {
+                write!(dest, "not fact: {}={}, {}={}", p1, ch1, p2, ch2)
+            }
+            Literal::Relation(relation, property, choices) => {
+                let names: Vec<String> = choices
+                    .iter()
+                    .map(|(p, ch)| format!("{}={}", p, ch))
+                    .collect();
+                write!(
+                    dest,
+                    "relation: {:?} {} on {}",
+                    relation,
+                    names.join(", "),
+                    property
+                )
+            }
+        }
+    }
+}
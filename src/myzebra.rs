@@ -1,22 +1,11 @@
+use crate::relation::Relation;
 use crate::zebra::{Zebra, ZebraBuilder};
 
-fn is_immediately_to_the_right(v1: &str, v2: &str) -> bool {
-    distance(v1, v2) == 1
-}
-
-fn is_next_to(v1: &str, v2: &str) -> bool {
-    i32::abs(distance(v1, v2)) == 1
-}
-
-fn distance(v1: &str, v2: &str) -> i32 {
-    let conv = |v| String::from(v).parse::<i32>().unwrap();
-    conv(v2) - conv(v1)
-}
-
 pub fn init_my_zebra<'a>() -> Zebra<'a> {
     ZebraBuilder::new()
         //    1. There are five houses.
         .set_object_count(5)
+        .choice("position", vec!["1", "2", "3", "4", "5"])
         //    2. The Englishman lives in the red house.
         .fact("nationality", "Englishman", "color", "red")
         //    3. The Spaniard owns the dog.
@@ -26,14 +15,10 @@ pub fn init_my_zebra<'a>() -> Zebra<'a> {
         //    5. The Ukrainian drinks tea.
         .fact("nationality", "Ukrainian", "beverage", "tea")
         //    6. The green house is immediately to the right of the ivory house.
-        .predicate(
-            "color",
-            "green",
-            "color",
-            "ivory",
+        .relation(
+            Relation::ImmediatelyRightOf,
             "position",
-            "position",
-            Box::new(is_immediately_to_the_right),
+            vec![("color", "green"), ("color", "ivory")],
         )
         //    7. The Old Gold smoker owns snails.
         .fact("smoke", "OldGold", "pet", "snail")
@@ -44,46 +29,46 @@ pub fn init_my_zebra<'a>() -> Zebra<'a> {
         //    10. The Norwegian lives in the first house.
         .fact("nationality", "Norwegian", "position", "1")
         //    11. The man who smokes Chesterfields lives in the house next to the man with the fox.
-        .predicate(
-            "smoke",
-            "Chesterfields",
-            "pet",
-            "fox",
-            "position",
+        .relation(
+            Relation::NextTo,
             "position",
-            Box::new(is_next_to),
+            vec![("smoke", "Chesterfields"), ("pet", "fox")],
         )
         //   12. Kools are smoked in the house next to the house where the horse is kept.
-        .predicate(
-            "smoke",
-            "Kools",
-            "pet",
-            "horse",
-            "position",
+        .relation(
+            Relation::NextTo,
             "position",
-            Box::new(is_next_to),
+            vec![("smoke", "Kools"), ("pet", "horse")],
         )
         //   13. The Lucky Strike smoker drinks orange juice.
         .fact("smoke", "LuckyStrike", "beverage", "juice")
         //    14. The Japanese smokes Parliaments.
         .fact("nationality", "Japanese", "smoke", "Parliaments")
         //    15. The Norwegian lives next to the blue house.
-        .predicate(
-            "nationality",
-            "Norwegian",
-            "color",
-            "blue",
+        .relation(
+            Relation::NextTo,
             "position",
-            "position",
-            Box::new(is_next_to),
+            vec![("nationality", "Norwegian"), ("color", "blue")],
         )
-        .choice("position", vec!["2", "4", "5"])
         .choice("beverage", vec!["watter"])
         .choice("pet", vec!["zebra"])
         .build()
 }
 
 #[test]
-pub fn test_distance() {
-    assert_eq!(2, distance("1", "3"));
+pub fn test_my_zebra() {
+    use crate::backtracking::find_first;
+
+    let solution = find_first(init_my_zebra()).unwrap();
+    let props = solution.get_properties();
+    let owner = |property, choice| {
+        let p = props.get_property(property).unwrap();
+        let ch = props.get_property_choice(property, choice).unwrap();
+        (0..5).find(|o| solution.get_determined(p, *o) == Some(ch))
+    };
+    assert_eq!(
+        owner("nationality", "Norwegian"),
+        owner("beverage", "watter")
+    );
+    assert_eq!(owner("nationality", "Japanese"), owner("pet", "zebra"));
 }
@@ -0,0 +1,218 @@
+use crate::backtracking::{Changed, Conflict, State};
+use std::fmt::{self, Display};
+
+/// The N-Queens puzzle: a queen in every row and no two queens attacking
+/// each other.
+#[derive(Debug, Clone)]
+pub struct Queens {
+    // Columns still possible for the queen of each row, one bit per column.
+    rows: Vec<u32>,
+}
+
+impl Queens {
+    pub fn new(size: usize) -> Queens {
+        if size == 0 || size > 32 {
+            panic!("Invalid board size {}, should be 1 to 32", size);
+        }
+        Queens {
+            rows: vec![full_row(size); size],
+        }
+    }
+
+    pub fn get_size(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns the column of the queen of the row, `None` while there are
+    /// several candidates left.
+    pub fn get_column(&self, row: usize) -> Option<usize> {
+        let columns = self.rows[row];
+        if columns.count_ones() == 1 {
+            Some(columns.trailing_zeros() as usize)
+        } else {
+            None
+        }
+    }
+
+    // Squares of the row attacked by a queen in the other row and column.
+    fn attacked(&self, row: usize, queen_row: usize, column: usize) -> u32 {
+        let distance = row.abs_diff(queen_row);
+        let mut result = 1 << column;
+        if column >= distance {
+            result |= 1 << (column - distance);
+        }
+        if column + distance < self.get_size() {
+            result |= 1 << (column + distance);
+        }
+        result
+    }
+}
+
+fn full_row(size: usize) -> u32 {
+    if size == 32 {
+        u32::MAX
+    } else {
+        (1 << size) - 1
+    }
+}
+
+/// Counts the solutions of the puzzle with the classic bitboard recursion,
+/// which is much faster than the generic search for large boards. Mirrored
+/// solutions are counted once by placing the first queen in the left half.
+pub fn count_solutions(size: usize) -> u64 {
+    fn count(all: u32, columns: u32, left: u32, right: u32) -> u64 {
+        if columns == all {
+            return 1;
+        }
+        let mut free = all & !(columns | left | right);
+        let mut result = 0;
+        while free != 0 {
+            let bit = free & free.wrapping_neg();
+            free ^= bit;
+            result += count(all, columns | bit, (left | bit) << 1, (right | bit) >> 1);
+        }
+        result
+    }
+    let all = full_row(size);
+    let mut result = 0;
+    for column in 0..size / 2 {
+        let bit = 1 << column;
+        result += 2 * count(all, bit, bit << 1, bit >> 1);
+    }
+    if size % 2 == 1 {
+        let bit = 1 << (size / 2);
+        result += count(all, bit, bit << 1, bit >> 1);
+    }
+    result
+}
+
+impl Display for Queens {
+    fn fmt(&self, dest: &mut fmt::Formatter) -> fmt::Result {
+        for row in 0..self.get_size() {
+            for column in 0..self.get_size() {
+                let square = match self.get_column(row) {
+                    Some(c) if c == column => 'Q',
+                    _ if self.rows[row] & (1 << column) != 0 => '?',
+                    _ => '.',
+                };
+                write!(dest, "{}", square)?;
+            }
+            writeln!(dest)?;
+        }
+        Ok(())
+    }
+}
+
+impl State for Queens {
+    fn is_solution(&self) -> bool {
+        self.rows.iter().all(|columns| columns.count_ones() == 1)
+    }
+
+    fn branch(&self) -> Vec<Self> {
+        // the row with the fewest candidates
+        let row = match (0..self.get_size())
+            .filter(|r| self.rows[*r].count_ones() > 1)
+            .min_by_key(|r| self.rows[*r].count_ones())
+        {
+            Some(row) => row,
+            None => return vec![],
+        };
+        let mut result = vec![];
+        let mut columns = self.rows[row];
+        while columns != 0 {
+            let bit = columns & columns.wrapping_neg();
+            columns ^= bit;
+            let mut q = self.clone();
+            q.rows[row] = bit;
+            result.push(q);
+        }
+        result
+    }
+
+    fn propagate(&mut self) -> Result<Changed, Conflict> {
+        let mut change_counter = 0;
+        if !self.apply_attacks(&mut change_counter) || !self.apply_columns(&mut change_counter) {
+            return Err(Conflict);
+        }
+        Ok(Changed::from_count(change_counter))
+    }
+}
+
+impl Queens {
+    // Removes the squares attacked by the placed queens.
+    fn apply_attacks(&mut self, change_counter: &mut i32) -> bool {
+        for queen_row in 0..self.get_size() {
+            let column = match self.get_column(queen_row) {
+                Some(column) => column,
+                None => continue,
+            };
+            for row in (0..self.get_size()).filter(|r| *r != queen_row) {
+                let attacked = self.attacked(row, queen_row, column);
+                if self.rows[row] & attacked != 0 {
+                    self.rows[row] &= !attacked;
+                    *change_counter += 1;
+                    if self.rows[row] == 0 {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    // Every column holds a queen too.
+    fn apply_columns(&mut self, change_counter: &mut i32) -> bool {
+        for column in 0..self.get_size() {
+            let bit = 1 << column;
+            let mut rows = (0..self.get_size()).filter(|r| self.rows[*r] & bit != 0);
+            match (rows.next(), rows.next()) {
+                (None, _) => return false,
+                (Some(row), None) if self.rows[row] != bit => {
+                    self.rows[row] = bit;
+                    *change_counter += 1;
+                }
+                _ => {}
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::backtracking::{count_all, find_first};
+
+    #[test]
+    fn test_first_solution() {
+        let solution = find_first(Queens::new(8)).unwrap();
+        let columns: Vec<usize> = (0..8).map(|r| solution.get_column(r).unwrap()).collect();
+        for r1 in 0..8 {
+            for r2 in (r1 + 1)..8 {
+                assert_ne!(columns[r1], columns[r2]);
+                assert_ne!(r2 - r1, columns[r1].abs_diff(columns[r2]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_apply_attacks() {
+        let mut queens = Queens::new(4);
+        queens.rows[0] = 0b0010;
+        let mut change_counter = 0;
+        assert!(queens.apply_attacks(&mut change_counter));
+        // each forced queen attacks the next rows in turn
+        assert_eq!(vec![0b0010, 0b1000, 0b0001, 0b0100], queens.rows);
+        assert!(queens.is_solution());
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        let known = [1, 0, 0, 2, 10, 4, 40, 92, 352, 724];
+        for (i, count) in known.iter().enumerate() {
+            assert_eq!(*count, count_solutions(i + 1));
+            assert_eq!(*count as usize, count_all(Queens::new(i + 1)));
+        }
+    }
+}
@@ -0,0 +1,827 @@
+use crate::expression::{ArithmeticClue, Target, Term};
+use crate::relation::{Arrangement, Comparison};
+use crate::zebra::{Condition, Counted, Fact, GridClue, RelationClue, Zebra};
+
+// The choice of a property of the object in a solved state.
+pub(crate) fn choice(zebra: &Zebra, property: usize, object: usize) -> usize {
+    zebra
+        .get_determined(property, object)
+        .expect("Clues are checked on solved states only")
+}
+
+// Objects having the choice.
+pub(crate) fn owners(zebra: &Zebra, (property, ch): (usize, usize)) -> Vec<usize> {
+    let object_count = zebra.get_properties().get_object_count();
+    (0..object_count)
+        .filter(|o| choice(zebra, property, *o) == ch)
+        .collect()
+}
+
+fn has(zebra: &Zebra, (property, ch): (usize, usize), object: usize) -> bool {
+    choice(zebra, property, object) == ch
+}
+
+// "property=choice"
+pub(crate) fn name(zebra: &Zebra, (property, ch): (usize, usize)) -> String {
+    let props = zebra.get_properties();
+    format!(
+        "{}={}",
+        props.get_property_name(property),
+        props.get_property_choice_name_by_nr(property, ch)
+    )
+}
+
+pub(crate) fn object_name<'a>(zebra: &Zebra<'a>, object: usize) -> &'a str {
+    zebra.get_properties().get_object_name(object)
+}
+
+// The choice of a property of the object, by name.
+pub(crate) fn choice_name<'a>(zebra: &Zebra<'a>, property: usize, object: usize) -> &'a str {
+    zebra
+        .get_properties()
+        .get_property_choice_name_by_nr(property, choice(zebra, property, object))
+}
+
+// The objects having the choice allowed to fewer objects have the other
+// choice too. Choices allowed to as many objects belong to the same ones.
+pub(crate) fn check_fact(zebra: &Zebra, f: &Fact) -> Result<(), String> {
+    let props = zebra.get_properties();
+    let max1 = props.get_occurrence(f.choice1.0, f.choice1.1).1;
+    let max2 = props.get_occurrence(f.choice2.0, f.choice2.1).1;
+    let mut implications = vec![];
+    if max1 <= max2 {
+        implications.push((f.choice1, f.choice2));
+    }
+    if max2 <= max1 {
+        implications.push((f.choice2, f.choice1));
+    }
+    for (choice1, choice2) in implications {
+        if let Some(o) = owners(zebra, choice1)
+            .into_iter()
+            .find(|o| !has(zebra, choice2, *o))
+        {
+            return Err(format!(
+                "{} has {} but {}={}",
+                object_name(zebra, o),
+                name(zebra, choice1),
+                props.get_property_name(choice2.0),
+                choice_name(zebra, choice2.0, o)
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn check_negative_fact(zebra: &Zebra, f: &Fact) -> Result<(), String> {
+    match owners(zebra, f.choice1)
+        .into_iter()
+        .find(|o| has(zebra, f.choice2, *o))
+    {
+        Some(o) => Err(format!(
+            "{} has both {} and {}",
+            object_name(zebra, o),
+            name(zebra, f.choice1),
+            name(zebra, f.choice2)
+        )),
+        None => Ok(()),
+    }
+}
+
+// Whether objects having the choices of the relation, the object at `fixed`
+// among them, are in the relation. `objects` holds the ones picked so far.
+fn has_relation_support(
+    zebra: &Zebra,
+    r: &RelationClue,
+    fixed: Option<(usize, usize)>,
+    objects: &mut Vec<usize>,
+) -> bool {
+    let j = objects.len();
+    if j == r.choices.len() {
+        let props = zebra.get_properties();
+        let places: Vec<usize> = objects
+            .iter()
+            .map(|o| choice(zebra, r.property, *o))
+            .collect();
+        let place_count = props.get_choice_count(r.property);
+        let arrangement = props.get_arrangement(r.property);
+        return r.relation.holds_in(&places, place_count, arrangement) != r.negated;
+    }
+    let candidates = match fixed {
+        Some((i, object)) if i == j => vec![object],
+        _ => owners(zebra, r.choices[j]),
+    };
+    for object in candidates {
+        objects.push(object);
+        if has_relation_support(zebra, r, fixed, objects) {
+            return true;
+        }
+        objects.pop();
+    }
+    false
+}
+
+// Every object having one of the choices is in the relation with some
+// objects having the other ones.
+pub(crate) fn check_relation(zebra: &Zebra, r: &RelationClue) -> Result<(), String> {
+    for (i, c) in r.choices.iter().enumerate() {
+        for o in owners(zebra, *c) {
+            if !has_relation_support(zebra, r, Some((i, o)), &mut vec![]) {
+                let places: Vec<String> = r
+                    .choices
+                    .iter()
+                    .map(|c| {
+                        let places: Vec<&str> = owners(zebra, *c)
+                            .into_iter()
+                            .map(|o| choice_name(zebra, r.property, o))
+                            .collect();
+                        match places.len() {
+                            0 => format!("nobody has {}", name(zebra, *c)),
+                            _ => format!("{} is at {}", name(zebra, *c), places.join(", ")),
+                        }
+                    })
+                    .collect();
+                return Err(format!(
+                    "{}{:?} does not hold on {}: {}",
+                    if r.negated { "not " } else { "" },
+                    r.relation,
+                    zebra.get_properties().get_property_name(r.property),
+                    places.join(", ")
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn condition_holds(zebra: &Zebra, condition: &Condition) -> bool {
+    match condition {
+        Condition::Same(f) => {
+            owners(zebra, f.choice1)
+                .into_iter()
+                .any(|o| has(zebra, f.choice2, o))
+                && check_fact(zebra, f).is_ok()
+        }
+        Condition::Different(f) => !condition_holds(zebra, &Condition::Same(f.clone())),
+        Condition::Relation(r) => {
+            has_relation_support(zebra, r, None, &mut vec![]) && check_relation(zebra, r).is_ok()
+        }
+    }
+}
+
+fn count_holding(zebra: &Zebra, conditions: &[Condition]) -> usize {
+    conditions
+        .iter()
+        .filter(|c| condition_holds(zebra, c))
+        .count()
+}
+
+// Every object having one of the choices compares with some object having
+// the other one.
+fn check_comparison(
+    zebra: &Zebra,
+    property: usize,
+    comparison: Comparison,
+    choice1: (usize, usize),
+    choice2: (usize, usize),
+) -> Result<(), String> {
+    let props = zebra.get_properties();
+    let value = |o: usize| props.get_value(property, choice(zebra, property, o));
+    let owners2 = owners(zebra, choice2);
+    if owners2.is_empty() {
+        return Err(format!("nobody has {}", name(zebra, choice2)));
+    }
+    for o1 in owners(zebra, choice1) {
+        if !owners2
+            .iter()
+            .any(|o2| comparison.holds(value(o1), value(*o2)))
+        {
+            let values: Vec<String> = owners2.iter().map(|o2| value(*o2).to_string()).collect();
+            return Err(format!(
+                "{} with {} has {} {}, not {:?} {} of {}",
+                object_name(zebra, o1),
+                name(zebra, choice1),
+                props.get_property_name(property),
+                value(o1),
+                comparison,
+                values.join(", "),
+                name(zebra, choice2)
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn check_grid_relation(zebra: &Zebra, g: &GridClue) -> Result<(), String> {
+    let cell = |o: usize| (choice(zebra, g.rows, o), choice(zebra, g.columns, o));
+    let owners1 = owners(zebra, g.choice1);
+    let owners2 = owners(zebra, g.choice2);
+    let holds = |o1: usize, o2: usize| o1 != o2 && g.relation.holds(cell(o1), cell(o2));
+    let unsupported = owners1
+        .iter()
+        .find(|o1| !owners2.iter().any(|o2| holds(**o1, *o2)))
+        .map(|o1| (*o1, g.choice1, g.choice2))
+        .or_else(|| {
+            owners2
+                .iter()
+                .find(|o2| !owners1.iter().any(|o1| holds(*o1, **o2)))
+                .map(|o2| (*o2, g.choice2, g.choice1))
+        });
+    match unsupported {
+        Some((o, own, other)) => Err(format!(
+            "{} with {} at {}, {} has no object with {} {:?}",
+            object_name(zebra, o),
+            name(zebra, own),
+            choice_name(zebra, g.rows, o),
+            choice_name(zebra, g.columns, o),
+            name(zebra, other),
+            g.relation
+        )),
+        None => Ok(()),
+    }
+}
+
+// Objects the target selects.
+fn selected(zebra: &Zebra, target: &Target) -> Vec<usize> {
+    match target {
+        Target::Has(c) => owners(zebra, *c),
+        Target::Offset(inner, property, offset) => {
+            let props = zebra.get_properties();
+            let place_count = props.get_choice_count(*property) as i64;
+            let mut result = vec![];
+            for o in selected(zebra, inner) {
+                let mut place = choice(zebra, *property, o) as i64 + offset;
+                if props.get_arrangement(*property) == Arrangement::Circle {
+                    place = place.rem_euclid(place_count);
+                }
+                for o2 in 0..props.get_object_count() {
+                    if o2 != o && choice(zebra, *property, o2) as i64 == place {
+                        result.push(o2);
+                    }
+                }
+            }
+            result
+        }
+    }
+}
+
+// Values of the term for every way to pick one of the objects each target
+// selects. Empty when a target selects nobody.
+fn values(zebra: &Zebra, term: &Term) -> Vec<i64> {
+    let combine = |a: &Term, b: &Term, f: fn(i64, i64) -> i64| {
+        let b = values(zebra, b);
+        values(zebra, a)
+            .into_iter()
+            .flat_map(|a| b.iter().map(move |b| f(a, *b)))
+            .collect()
+    };
+    match term {
+        Term::Const(c) => vec![*c],
+        Term::Value(property, target) => {
+            let props = zebra.get_properties();
+            selected(zebra, target)
+                .into_iter()
+                .map(|o| props.get_value(*property, choice(zebra, *property, o)))
+                .collect()
+        }
+        Term::Add(a, b) => combine(a, b, |a, b| a + b),
+        Term::Sub(a, b) => combine(a, b, |a, b| a - b),
+        Term::Mul(a, b) => combine(a, b, |a, b| a * b),
+    }
+}
+
+// The sides compare for some choice of the selected objects, the only one
+// unless a selecting choice is shared.
+fn check_arithmetic(zebra: &Zebra, c: &ArithmeticClue) -> Result<(), String> {
+    let lhs = values(zebra, &c.lhs);
+    let rhs = values(zebra, &c.rhs);
+    if lhs
+        .iter()
+        .any(|l| rhs.iter().any(|r| c.comparison.holds(*l, *r)))
+    {
+        return Ok(());
+    }
+    let side = |values: &[i64]| match values.len() {
+        0 => "undefined".to_string(),
+        _ => {
+            let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            values.join(" or ")
+        }
+    };
+    Err(format!(
+        "the left side is {}, the right side {}, not {:?}",
+        side(&lhs),
+        side(&rhs),
+        c.comparison
+    ))
+}
+
+/// Checks the clue in a solved state, evaluating it directly on the choices
+/// of the objects rather than by the propagators. The error explains why the
+/// clue does not hold.
+pub fn check_clue(zebra: &Zebra, clue: usize) -> Result<(), String> {
+    let props = zebra.get_properties();
+    if let Some(f) = props.facts.iter().find(|f| f.clue == clue) {
+        return check_fact(zebra, f);
+    }
+    if let Some(f) = props.negative_facts.iter().find(|f| f.clue == clue) {
+        return check_negative_fact(zebra, f);
+    }
+    if let Some(pred) = props.predicates.iter().find(|p| p.clue == clue) {
+        for o1 in owners(zebra, pred.choice1) {
+            for o2 in owners(zebra, pred.choice2) {
+                let name3 = choice_name(zebra, pred.property3, o1);
+                let name4 = choice_name(zebra, pred.property4, o2);
+                if !(pred.test)(name3, name4) {
+                    return Err(format!(
+                        "the test fails for {}={} of {} and {}={} of {}",
+                        props.get_property_name(pred.property3),
+                        name3,
+                        object_name(zebra, o1),
+                        props.get_property_name(pred.property4),
+                        name4,
+                        object_name(zebra, o2)
+                    ));
+                }
+            }
+        }
+        return Ok(());
+    }
+    if let Some(r) = props.relations.iter().find(|r| r.clue == clue) {
+        return check_relation(zebra, r);
+    }
+    if let Some(d) = props.disjunctions.iter().find(|d| d.clue == clue) {
+        let count = count_holding(zebra, &d.conditions);
+        return match count {
+            0 => Err("none of the alternatives holds".to_string()),
+            1 => Ok(()),
+            _ if d.exclusive => Err(format!("{} of the alternatives hold", count)),
+            _ => Ok(()),
+        };
+    }
+    if let Some(i) = props.implications.iter().find(|i| i.clue == clue) {
+        if condition_holds(zebra, &i.condition) && !condition_holds(zebra, &i.consequence) {
+            return Err("the condition holds but the consequence does not".to_string());
+        }
+        return Ok(());
+    }
+    if let Some(c) = props.cardinalities.iter().find(|c| c.clue == clue) {
+        let count = match &c.counted {
+            Counted::Conditions(conditions) => count_holding(zebra, conditions),
+            Counted::Objects(filter) => (0..props.get_object_count())
+                .filter(|o| {
+                    filter
+                        .0
+                        .iter()
+                        .all(|(p, set)| set.contains(&choice(zebra, *p, *o)))
+                })
+                .count(),
+        };
+        if count < c.min || count > c.max {
+            return Err(format!("{} hold, should be {} to {}", count, c.min, c.max));
+        }
+        return Ok(());
+    }
+    if let Some(c) = props.comparisons.iter().find(|c| c.clue == clue) {
+        check_comparison(zebra, c.property, c.comparison, c.choice1, c.choice2)?;
+        return check_comparison(
+            zebra,
+            c.property,
+            c.comparison.mirror(),
+            c.choice2,
+            c.choice1,
+        );
+    }
+    if let Some(c) = props.arithmetic.iter().find(|c| c.clue == clue) {
+        return check_arithmetic(zebra, c);
+    }
+    if let Some(g) = props.grid_relations.iter().find(|g| g.clue == clue) {
+        return check_grid_relation(zebra, g);
+    }
+    Ok(())
+}
+
+/// Whether the clue holds in a solved state, see `check_clue`.
+pub fn is_clue_satisfied(zebra: &Zebra, clue: usize) -> bool {
+    check_clue(zebra, clue).is_ok()
+}
+
+// Whether the solved state keeps the number of owners of the choices and
+// the cells of the grids, and satisfies the clues.
+fn is_valid(zebra: &Zebra) -> bool {
+    let props = zebra.get_properties();
+    let object_count = props.get_object_count();
+    for &(rows, columns) in &props.grids {
+        let cell = |o: usize| (choice(zebra, rows, o), choice(zebra, columns, o));
+        for o1 in 0..object_count {
+            if (o1 + 1..object_count).any(|o2| cell(o1) == cell(o2)) {
+                return false;
+            }
+        }
+    }
+    (0..zebra.get_clue_count())
+        .filter(|c| zebra.is_clue_enforced(*c))
+        .all(|c| is_clue_satisfied(zebra, c))
+}
+
+// Every way to give the objects a choice of the property allowed by the
+// state and by the number of owners of the choices.
+fn property_assignments(zebra: &Zebra, property: usize) -> Vec<Vec<usize>> {
+    let props = zebra.get_properties();
+    let object_count = props.get_object_count();
+    let choice_count = props.get_choice_count(property);
+    let mut result: Vec<Vec<usize>> = vec![vec![]];
+    for o in 0..object_count {
+        result = result
+            .into_iter()
+            .flat_map(|a| {
+                (0..choice_count)
+                    .filter(move |ch| zebra.is_choice_enabled(property, o, *ch))
+                    .map(move |ch| {
+                        let mut a = a.clone();
+                        a.push(ch);
+                        a
+                    })
+            })
+            .collect();
+    }
+    result.retain(|a| {
+        (0..choice_count).all(|ch| {
+            let count = a.iter().filter(|c| **c == ch).count();
+            let (min, max) = props.get_occurrence(property, ch);
+            zebra.is_choice_exempt(property, ch) || (min <= count && count <= max)
+        })
+    });
+    result
+}
+
+fn combine<'a>(
+    assignments: &[Vec<Vec<usize>>],
+    property: usize,
+    current: &mut Zebra<'a>,
+    result: &mut Vec<Zebra<'a>>,
+) {
+    if property == assignments.len() {
+        if is_valid(current) {
+            result.push(current.clone());
+        }
+        return;
+    }
+    for a in &assignments[property] {
+        for (object, ch) in a.iter().enumerate() {
+            current.determine_choice(property, object, *ch);
+        }
+        combine(assignments, property + 1, current, result);
+    }
+}
+
+/// Every solution of the puzzle from the state, found by trying all the
+/// combinations of choices and checking each clue directly, without any
+/// propagation. Meant for cross-checking the search on small puzzles.
+pub fn find_all_brute_force<'a>(zebra: &Zebra<'a>) -> Vec<Zebra<'a>> {
+    let property_count = zebra.get_properties().get_property_count();
+    let assignments: Vec<Vec<Vec<usize>>> = (0..property_count)
+        .map(|p| property_assignments(zebra, p))
+        .collect();
+    let mut result = vec![];
+    combine(&assignments, 0, &mut zebra.clone(), &mut result);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::backtracking::{find_all, find_first};
+    use crate::expression::{Expr, Selector};
+    use crate::literal::Literal;
+    use crate::relation::{Arrangement, GridRelation, Relation};
+    use crate::zebra::{PredicateTest, ZebraBuilder};
+
+    // xorshift64, good enough to vary the puzzles
+    struct Random(u64);
+
+    impl Random {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    const PROPERTIES: [&str; 3] = ["name", "color", "place"];
+    const CHOICES: [[&str; 4]; 3] = [
+        ["Ann", "Bob", "Cid", "Dan"],
+        ["red", "green", "blue", "white"],
+        ["1", "2", "3", "4"],
+    ];
+
+    // Distinct random choices.
+    fn random_choices(
+        random: &mut Random,
+        n: usize,
+        count: usize,
+    ) -> Vec<(&'static str, &'static str)> {
+        let mut result = vec![];
+        while result.len() < count {
+            let p = random.below(PROPERTIES.len());
+            let c = (PROPERTIES[p], CHOICES[p][random.below(n)]);
+            if !result.contains(&c) {
+                result.push(c);
+            }
+        }
+        result
+    }
+
+    fn random_relation(random: &mut Random, arrangement: Arrangement) -> Relation {
+        let relations = [
+            Relation::LeftOf,
+            Relation::RightOf,
+            Relation::ImmediatelyLeftOf,
+            Relation::ImmediatelyRightOf,
+            Relation::NextTo,
+            Relation::Distance(2),
+            Relation::Between,
+            Relation::AtEnd,
+            Relation::SameHalf,
+            Relation::Opposite,
+        ];
+        loop {
+            let relation = relations[random.below(relations.len())];
+            if relation.is_defined_in(arrangement) {
+                return relation;
+            }
+        }
+    }
+
+    fn random_literal(random: &mut Random, n: usize, arrangement: Arrangement) -> Literal<'static> {
+        match random.below(3) {
+            0 => {
+                let c = random_choices(random, n, 2);
+                Literal::fact(c[0].0, c[0].1, c[1].0, c[1].1)
+            }
+            1 => {
+                let c = random_choices(random, n, 2);
+                Literal::not_fact(c[0].0, c[0].1, c[1].0, c[1].1)
+            }
+            _ => {
+                let relation = random_relation(random, arrangement);
+                Literal::relation(
+                    relation,
+                    "place",
+                    random_choices(random, n, relation.arity()),
+                )
+            }
+        }
+    }
+
+    fn random_comparison(random: &mut Random) -> Comparison {
+        let k = 1 + random.below(2) as i64;
+        let comparisons = [
+            Comparison::Less,
+            Comparison::LessOrEqual,
+            Comparison::Greater,
+            Comparison::GreaterOrEqual,
+            Comparison::Equal,
+            Comparison::NotEqual,
+            Comparison::Difference(k),
+            Comparison::Difference(-k),
+            Comparison::AbsDifference(k),
+        ];
+        comparisons[random.below(comparisons.len())]
+    }
+
+    fn random_test(random: &mut Random) -> PredicateTest {
+        match random.below(3) {
+            0 => Box::new(|a, b| a < b),
+            1 => Box::new(|a, b| a != b),
+            _ => Box::new(|a, b| a.len() == b.len()),
+        }
+    }
+
+    // The object having a random name, maybe moved along the places.
+    fn random_selector(random: &mut Random, n: usize) -> Selector<'static> {
+        let selector = Selector::has("name", CHOICES[0][random.below(n)]);
+        match random.below(3) {
+            0 => selector.offset("place", 1),
+            1 => selector.offset("place", -1),
+            _ => selector,
+        }
+    }
+
+    fn random_puzzle(random: &mut Random) -> Zebra<'static> {
+        let n = 2 + random.below(3);
+        let mut builder = ZebraBuilder::new();
+        builder
+            .set_object_count(n)
+            .choice("name", CHOICES[0][..n].to_vec())
+            .choice("color", CHOICES[1][..n].to_vec())
+            .numeric("place", CHOICES[2][..n].to_vec());
+        match random.below(3) {
+            // colors shared by several objects or by none
+            0 => {
+                builder.occurrence("color", CHOICES[1][..n].to_vec(), 0, n);
+            }
+            // some objects without a color
+            1 => {
+                builder
+                    .occurrence("color", CHOICES[1][..n].to_vec(), 0, 1)
+                    .optional("color");
+            }
+            _ => {}
+        }
+        let arrangement = if random.below(4) == 0 {
+            Arrangement::Circle
+        } else {
+            Arrangement::Line
+        };
+        builder.arrangement("place", arrangement);
+        for _ in 0..1 + random.below(5) {
+            match random.below(10) {
+                0 => {
+                    let c = random_choices(random, n, 2);
+                    if c[0].0 != c[1].0 {
+                        builder.fact(c[0].0, c[0].1, c[1].0, c[1].1);
+                    }
+                }
+                1 => {
+                    let c = random_choices(random, n, 2);
+                    builder.not_fact(c[0].0, c[0].1, c[1].0, c[1].1);
+                }
+                2 => {
+                    let relation = random_relation(random, arrangement);
+                    let c = random_choices(random, n, relation.arity());
+                    builder.relation(relation, "place", c);
+                }
+                3 => {
+                    let comparison = random_comparison(random);
+                    let c = random_choices(random, n, 2);
+                    builder.compare("place", comparison, c[0], c[1]);
+                }
+                4 => {
+                    builder.either(vec![
+                        random_literal(random, n, arrangement),
+                        random_literal(random, n, arrangement),
+                    ]);
+                }
+                5 => {
+                    let literals = (0..3)
+                        .map(|_| random_literal(random, n, arrangement))
+                        .collect();
+                    builder.at_most(1, literals);
+                }
+                6 => {
+                    let c = random_choices(random, n, 2);
+                    let property3 = PROPERTIES[random.below(PROPERTIES.len())];
+                    let property4 = PROPERTIES[random.below(PROPERTIES.len())];
+                    let test = random_test(random);
+                    builder.predicate(c[0].0, c[0].1, c[1].0, c[1].1, property3, property4, test);
+                }
+                7 => {
+                    let lhs = Expr::value("place", random_selector(random, n));
+                    let rhs = match random.below(3) {
+                        0 => Expr::value("place", random_selector(random, n)) + Expr::Const(1),
+                        1 => Expr::Const(2) * Expr::value("color", random_selector(random, n)),
+                        _ => Expr::Const(random.below(n) as i64 + 1),
+                    };
+                    builder.arithmetic(lhs, random_comparison(random), rhs);
+                }
+                8 => {
+                    let color = CHOICES[1][random.below(n)];
+                    let places: Vec<&str> = CHOICES[2][..n]
+                        .iter()
+                        .copied()
+                        .filter(|_| random.below(2) == 0)
+                        .collect();
+                    let min = random.below(2);
+                    builder.object_cardinality(
+                        vec![("color", vec![color]), ("place", places)],
+                        min,
+                        min + random.below(2),
+                    );
+                }
+                _ => {
+                    builder.implies(
+                        random_literal(random, n, arrangement),
+                        random_literal(random, n, arrangement),
+                    );
+                }
+            }
+        }
+        builder.build()
+    }
+
+    // Four objects on two floors of two rooms.
+    fn random_grid_puzzle(random: &mut Random) -> Zebra<'static> {
+        let mut builder = ZebraBuilder::new();
+        builder
+            .set_object_count(4)
+            .choice("name", CHOICES[0].to_vec())
+            .choice("color", CHOICES[1].to_vec())
+            .grid("floor", vec!["1", "2"], "side", vec!["left", "right"]);
+        let choice = |random: &mut Random| match random.below(2) {
+            0 => ("name", CHOICES[0][random.below(4)]),
+            _ => ("color", CHOICES[1][random.below(4)]),
+        };
+        for _ in 0..1 + random.below(4) {
+            match random.below(3) {
+                0 => {
+                    let (p, ch) = choice(random);
+                    let (cell, value) = match random.below(2) {
+                        0 => ("floor", ["1", "2"][random.below(2)]),
+                        _ => ("side", ["left", "right"][random.below(2)]),
+                    };
+                    builder.fact(p, ch, cell, value);
+                }
+                1 => {
+                    let (p1, ch1) = choice(random);
+                    let (p2, ch2) = choice(random);
+                    builder.not_fact(p1, ch1, p2, ch2);
+                }
+                _ => {
+                    let relations = [
+                        GridRelation::DirectlyAbove,
+                        GridRelation::DirectlyBelow,
+                        GridRelation::SameRow,
+                        GridRelation::SameColumn,
+                        GridRelation::Diagonal,
+                        GridRelation::Distance(1 + random.below(2)),
+                    ];
+                    let relation = relations[random.below(relations.len())];
+                    let (c1, c2) = (choice(random), choice(random));
+                    builder.grid_relation(relation, "floor", "side", c1, c2);
+                }
+            }
+        }
+        builder.build()
+    }
+
+    fn sorted(solutions: Vec<Zebra>) -> Vec<String> {
+        let mut result: Vec<String> = solutions.iter().map(|s| s.to_string()).collect();
+        result.sort();
+        result
+    }
+
+    #[test]
+    fn test_find_all_brute_force() {
+        let zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("name", vec!["Ann", "Bob", "Cid"])
+            .choice("age", vec!["20", "30", "40"])
+            .predicate(
+                "name",
+                "Ann",
+                "name",
+                "Bob",
+                "age",
+                "age",
+                Box::new(|a, b| a > b),
+            )
+            .predicate(
+                "name",
+                "Bob",
+                "name",
+                "Cid",
+                "age",
+                "age",
+                Box::new(|a, b| a > b),
+            )
+            .build();
+        let solutions = find_all_brute_force(&zebra);
+        assert_eq!(1, solutions.len());
+        assert_eq!(Some("40"), solutions[0].get_determined_name("Ann", "age"));
+        assert_eq!(Some("20"), solutions[0].get_determined_name("Cid", "age"));
+    }
+
+    // The search must find exactly the solutions of the brute force, which
+    // catches propagators removing valid choices and a wrong
+    // `is_solution`, as when it swapped the object and the property.
+    #[test]
+    fn test_random_puzzles() {
+        let mut random = Random(0x2545_f491_4f6c_dd1d);
+        for _ in 0..100 {
+            let zebra = match random.below(4) {
+                0 => random_grid_puzzle(&mut random),
+                _ => random_puzzle(&mut random),
+            };
+            let clues: Vec<String> = (0..zebra.get_clue_count())
+                .map(|c| zebra.get_clue_text(c))
+                .collect();
+            let expected = sorted(find_all_brute_force(&zebra));
+            assert_eq!(
+                expected,
+                sorted(find_all(zebra.clone())),
+                "{}",
+                clues.join("\n")
+            );
+            match find_first(zebra) {
+                Some(s) => assert!(expected.contains(&s.to_string()), "{}", clues.join("\n")),
+                None => assert!(expected.is_empty(), "{}", clues.join("\n")),
+            }
+        }
+    }
+}
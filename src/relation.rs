@@ -0,0 +1,271 @@
+/// Relations between objects given by their places in an ordered property.
+/// A place is the index of the choice, in the order in which the choices were
+/// declared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    /// The first object is somewhere left of the second one. On a circle
+    /// everybody is left of everybody going round, so it means the seat
+    /// right to the left, the same as `ImmediatelyLeftOf`.
+    LeftOf,
+    /// The first object is somewhere right of the second one. On a circle it
+    /// means the seat right to the right, the same as `ImmediatelyRightOf`.
+    RightOf,
+    ImmediatelyLeftOf,
+    ImmediatelyRightOf,
+    NextTo,
+    /// The objects are exactly `k` places apart, in either direction.
+    Distance(usize),
+    /// The first object is somewhere between the other two.
+    Between,
+    /// The object is at the first or at the last place. Only defined on a
+    /// line.
+    AtEnd,
+    /// Both objects are in the same half. The middle place of an odd count
+    /// belongs to neither half. Only defined on a line.
+    SameHalf,
+    /// The objects sit across each other at a round table with an even
+    /// number of seats. Only defined on a circle.
+    Opposite,
+}
+
+/// How the places of an ordered property are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arrangement {
+    /// Places in a row, the first and the last one are the ends.
+    Line,
+    /// Seats around a round table, the last seat is next to the first one.
+    /// Left means the previous seat, right the next one.
+    Circle,
+}
+
+impl Relation {
+    /// Number of objects the relation talks about.
+    pub fn arity(&self) -> usize {
+        match self {
+            Relation::AtEnd => 1,
+            Relation::Between => 3,
+            _ => 2,
+        }
+    }
+
+    /// Whether the relation means something for places laid out by
+    /// `arrangement`. `ZebraBuilder::build` rejects the other clues.
+    pub fn is_defined_in(&self, arrangement: Arrangement) -> bool {
+        match arrangement {
+            Arrangement::Line => *self != Relation::Opposite,
+            Arrangement::Circle => !matches!(self, Relation::AtEnd | Relation::SameHalf),
+        }
+    }
+
+    /// Tests the relation on places of `arity()` objects, `count` being the
+    /// number of places in a line.
+    pub fn holds(&self, places: &[usize], count: usize) -> bool {
+        self.holds_in(places, count, Arrangement::Line)
+    }
+
+    /// Tests the relation on places laid out by `arrangement`. On a circle
+    /// `LeftOf` and `RightOf` mean the neighbouring seat, `Between` means
+    /// sitting between the other two and `Distance` counts the shorter way
+    /// round. Relations not `is_defined_in` the arrangement never hold.
+    pub fn holds_in(&self, places: &[usize], count: usize, arrangement: Arrangement) -> bool {
+        if arrangement == Arrangement::Circle {
+            return self.holds_in_circle(places, count);
+        }
+        let diff = |a: usize, b: usize| b as i64 - a as i64;
+        let half = |a: usize| {
+            if 2 * a + 1 < count {
+                Some(0)
+            } else if 2 * a + 1 > count {
+                Some(1)
+            } else {
+                None
+            }
+        };
+        match *self {
+            Relation::LeftOf => places[0] < places[1],
+            Relation::RightOf => places[0] > places[1],
+            Relation::ImmediatelyLeftOf => diff(places[0], places[1]) == 1,
+            Relation::ImmediatelyRightOf => diff(places[1], places[0]) == 1,
+            Relation::NextTo => diff(places[0], places[1]).abs() == 1,
+            Relation::Distance(k) => diff(places[0], places[1]).unsigned_abs() == k as u64,
+            Relation::Between => {
+                (places[1] < places[0] && places[0] < places[2])
+                    || (places[2] < places[0] && places[0] < places[1])
+            }
+            Relation::AtEnd => places[0] == 0 || places[0] + 1 == count,
+            Relation::SameHalf => half(places[0]).is_some() && half(places[0]) == half(places[1]),
+            Relation::Opposite => false,
+        }
+    }
+
+    fn holds_in_circle(&self, places: &[usize], count: usize) -> bool {
+        // seats from the first place to the second one going right
+        let diff = |a: usize, b: usize| (b + count - a) % count;
+        let distance = |a: usize, b: usize| diff(a, b).min(diff(b, a));
+        match *self {
+            Relation::LeftOf | Relation::ImmediatelyLeftOf => diff(places[0], places[1]) == 1,
+            Relation::RightOf | Relation::ImmediatelyRightOf => diff(places[1], places[0]) == 1,
+            Relation::NextTo => distance(places[0], places[1]) == 1,
+            Relation::Distance(k) => distance(places[0], places[1]) == k,
+            Relation::Between => {
+                (diff(places[1], places[0]) == 1 && diff(places[0], places[2]) == 1)
+                    || (diff(places[2], places[0]) == 1 && diff(places[0], places[1]) == 1)
+            }
+            Relation::AtEnd | Relation::SameHalf => false,
+            Relation::Opposite => 2 * diff(places[0], places[1]) == count,
+        }
+    }
+}
+
+/// Relations between two objects placed on a grid, given by their rows (floors,
+/// the first declared one at the bottom) and columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridRelation {
+    /// The first object is on the floor right above the second one, in the
+    /// same column.
+    DirectlyAbove,
+    DirectlyBelow,
+    SameRow,
+    SameColumn,
+    /// The objects are diagonal neighbours.
+    Diagonal,
+    /// The objects are `k` steps apart, counting rows and columns.
+    Distance(usize),
+}
+
+impl GridRelation {
+    /// Tests the relation on (row, column) cells of two objects.
+    pub fn holds(&self, cell1: (usize, usize), cell2: (usize, usize)) -> bool {
+        let rows = cell1.0 as i64 - cell2.0 as i64;
+        let columns = cell1.1 as i64 - cell2.1 as i64;
+        match *self {
+            GridRelation::DirectlyAbove => rows == 1 && columns == 0,
+            GridRelation::DirectlyBelow => rows == -1 && columns == 0,
+            GridRelation::SameRow => rows == 0,
+            GridRelation::SameColumn => columns == 0,
+            GridRelation::Diagonal => rows.abs() == 1 && columns.abs() == 1,
+            GridRelation::Distance(k) => (rows.abs() + columns.abs()) as usize == k,
+        }
+    }
+}
+
+/// Comparisons of the values of two objects in a numeric (or ordinal)
+/// property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+    /// The first value minus the second one is `k`.
+    Difference(i64),
+    /// The values differ by `k` in either direction.
+    AbsDifference(i64),
+}
+
+impl Comparison {
+    pub fn holds(&self, a: i64, b: i64) -> bool {
+        match *self {
+            Comparison::Less => a < b,
+            Comparison::LessOrEqual => a <= b,
+            Comparison::Greater => a > b,
+            Comparison::GreaterOrEqual => a >= b,
+            Comparison::Equal => a == b,
+            Comparison::NotEqual => a != b,
+            Comparison::Difference(k) => a - b == k,
+            Comparison::AbsDifference(k) => (a - b).abs() == k,
+        }
+    }
+
+    /// The same comparison with swapped operands.
+    pub fn mirror(&self) -> Comparison {
+        match *self {
+            Comparison::Less => Comparison::Greater,
+            Comparison::LessOrEqual => Comparison::GreaterOrEqual,
+            Comparison::Greater => Comparison::Less,
+            Comparison::GreaterOrEqual => Comparison::LessOrEqual,
+            Comparison::Difference(k) => Comparison::Difference(-k),
+            c => c,
+        }
+    }
+
+    /// Bounds of the first value given the bounds of the second one. The
+    /// result may be wider than the exact set of allowed values.
+    pub fn bounds(&self, low: i64, high: i64) -> (i64, i64) {
+        match *self {
+            Comparison::Less => (i64::MIN, high - 1),
+            Comparison::LessOrEqual => (i64::MIN, high),
+            Comparison::Greater => (low + 1, i64::MAX),
+            Comparison::GreaterOrEqual => (low, i64::MAX),
+            Comparison::Equal => (low, high),
+            Comparison::NotEqual => (i64::MIN, i64::MAX),
+            Comparison::Difference(k) => (low + k, high + k),
+            Comparison::AbsDifference(k) => (low - k, high + k),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_relations() {
+        assert!(Relation::LeftOf.holds(&[0, 3], 5));
+        assert!(!Relation::RightOf.holds(&[0, 3], 5));
+        assert!(Relation::ImmediatelyRightOf.holds(&[4, 3], 5));
+        assert!(!Relation::ImmediatelyLeftOf.holds(&[4, 3], 5));
+        assert!(Relation::NextTo.holds(&[4, 3], 5));
+        assert!(Relation::Distance(2).holds(&[0, 2], 5));
+        assert!(Relation::Distance(2).holds(&[2, 0], 5));
+        assert!(Relation::Between.holds(&[2, 4, 0], 5));
+        assert!(!Relation::Between.holds(&[0, 4, 2], 5));
+        assert!(Relation::AtEnd.holds(&[4], 5));
+        assert!(!Relation::AtEnd.holds(&[3], 5));
+        assert!(Relation::SameHalf.holds(&[0, 1], 5));
+        assert!(!Relation::SameHalf.holds(&[1, 2], 5));
+        assert!(Relation::SameHalf.holds(&[3, 5], 6));
+        assert!(!Relation::SameHalf.holds(&[2, 3], 6));
+        assert!(!Relation::Opposite.holds(&[0, 3], 6));
+    }
+
+    #[test]
+    fn test_circle_relations() {
+        let holds = |r: Relation, places: &[usize]| r.holds_in(places, 6, Arrangement::Circle);
+        assert!(holds(Relation::NextTo, &[0, 5]));
+        assert!(holds(Relation::LeftOf, &[5, 0]));
+        assert!(!holds(Relation::LeftOf, &[0, 5]));
+        assert!(holds(Relation::RightOf, &[0, 5]));
+        assert!(holds(Relation::Distance(2), &[1, 5]));
+        assert!(holds(Relation::Between, &[0, 1, 5]));
+        assert!(!holds(Relation::Between, &[2, 0, 4]));
+        assert!(holds(Relation::Opposite, &[1, 4]));
+        assert!(!Relation::Opposite.holds_in(&[1, 3], 5, Arrangement::Circle));
+        assert!(!holds(Relation::AtEnd, &[0]));
+    }
+
+    #[test]
+    fn test_grid_relations() {
+        assert!(GridRelation::DirectlyAbove.holds((2, 1), (1, 1)));
+        assert!(!GridRelation::DirectlyAbove.holds((2, 1), (1, 0)));
+        assert!(GridRelation::DirectlyBelow.holds((0, 1), (1, 1)));
+        assert!(GridRelation::SameRow.holds((1, 0), (1, 2)));
+        assert!(GridRelation::Diagonal.holds((1, 0), (0, 1)));
+        assert!(!GridRelation::Diagonal.holds((1, 0), (0, 2)));
+        assert!(GridRelation::Distance(3).holds((0, 0), (1, 2)));
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert!(Comparison::Less.holds(20, 25));
+        assert!(Comparison::Less.mirror().holds(25, 20));
+        assert!(Comparison::Difference(5).holds(25, 20));
+        assert!(Comparison::Difference(5).mirror().holds(20, 25));
+        assert!(Comparison::AbsDifference(5).holds(20, 25));
+        assert_eq!((i64::MIN, 29), Comparison::Less.bounds(20, 30));
+        assert_eq!((25, 35), Comparison::Difference(5).bounds(20, 30));
+    }
+}
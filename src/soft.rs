@@ -0,0 +1,78 @@
+use crate::backtracking::{find_best, Goal};
+use crate::zebra::Zebra;
+
+/// The best solution of a puzzle with soft clues.
+#[derive(Debug)]
+pub struct Compromise<'a> {
+    pub solution: Zebra<'a>,
+    /// Total weight of the violated soft clues.
+    pub penalty: i64,
+    pub violated: Vec<usize>,
+}
+
+// Enabled soft clues surely violated by the state.
+fn violated_clues(zebra: &Zebra) -> Vec<usize> {
+    (0..zebra.get_clue_count())
+        .filter(|c| zebra.is_clue_enabled(*c) && zebra.get_clue_weight(*c).is_some())
+        .filter(|c| zebra.is_clue_violated(*c))
+        .collect()
+}
+
+fn penalty(zebra: &Zebra) -> i64 {
+    violated_clues(zebra)
+        .into_iter()
+        .map(|c| zebra.get_clue_weight(c).unwrap())
+        .sum()
+}
+
+/// Finds the solution satisfying all hard clues with the least total weight
+/// of violated soft clues. Returns `None` when the hard clues have no
+/// solution.
+pub fn find_least_penalty<'a>(zebra: &Zebra<'a>) -> Option<Compromise<'a>> {
+    // the soft clues violated by a partial state stay violated
+    let solution = find_best(zebra.clone(), Goal::Minimize, penalty, penalty)?;
+    let violated = violated_clues(&solution);
+    Some(Compromise {
+        penalty: penalty(&solution),
+        solution,
+        violated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::relation::Relation;
+    use crate::zebra::ZebraBuilder;
+
+    #[test]
+    fn test_find_least_penalty() {
+        // Ann and Cid both want the first office, Ann wants to sit next to
+        // Bob, who cannot take the second one.
+        let zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("office", vec!["1", "2", "3"])
+            .choice("name", vec!["Ann", "Bob", "Cid"])
+            .fact("name", "Ann", "office", "1")
+            .soft(3)
+            .fact("name", "Cid", "office", "1")
+            .soft(2)
+            .relation(
+                Relation::NextTo,
+                "office",
+                vec![("name", "Ann"), ("name", "Bob")],
+            )
+            .soft(2)
+            .not_fact("name", "Bob", "office", "2")
+            .build();
+        let result = find_least_penalty(&zebra).unwrap();
+        // Cid gets the first office and Ann sits between the others
+        assert_eq!(3, result.penalty);
+        assert_eq!(vec![2], result.violated);
+        assert_eq!(
+            Some("Ann"),
+            result.solution.get_determined_name("2", "name")
+        );
+    }
+}
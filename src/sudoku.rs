@@ -0,0 +1,280 @@
+use crate::backtracking::{Changed, Conflict, State};
+use crate::learning::Decide;
+use std::fmt::{self, Display};
+
+/// Sudoku on a grid of `size` x `size` boxes, each of `size` x `size` cells,
+/// 3 for the classic puzzle. Values are numbered from 0 and written as `1` to
+/// `9` followed by `A`, `B` and so on.
+#[derive(Debug, Clone)]
+pub struct Sudoku {
+    size: usize,
+    // Values still possible in each cell, one bit per value, row by row.
+    cells: Vec<u64>,
+}
+
+const SYMBOLS: &str = "123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz@#";
+
+impl Sudoku {
+    /// Empty grid of `size` x `size` boxes.
+    pub fn new(size: usize) -> Sudoku {
+        if size == 0 || size > 7 {
+            panic!("Invalid box size {}, should be 1 to 7", size);
+        }
+        let side = size * size;
+        Sudoku {
+            size,
+            cells: vec![(1 << side) - 1; side * side],
+        }
+    }
+
+    /// Parses the common one line format: the cells row by row, `.` or `0`
+    /// for the empty ones. Whitespace is ignored and the size follows from
+    /// the length, 81 cells for the classic puzzle. Returns `None` when the
+    /// text is not a grid.
+    pub fn parse(text: &str) -> Option<Sudoku> {
+        let chars: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+        let size = (1..=7).find(|s| s * s * s * s == chars.len())?;
+        let mut result = Sudoku::new(size);
+        for (cell, c) in chars.into_iter().enumerate() {
+            if c == '.' || c == '0' {
+                continue;
+            }
+            let value = SYMBOLS
+                .chars()
+                .take(result.get_side())
+                .position(|s| s == c)?;
+            result.cells[cell] = 1 << value;
+        }
+        Some(result)
+    }
+
+    pub fn get_size(&self) -> usize {
+        self.size
+    }
+
+    /// Number of cells in a row, column or box.
+    pub fn get_side(&self) -> usize {
+        self.size * self.size
+    }
+
+    /// Returns the value of the cell, `None` while there are several
+    /// candidates left.
+    pub fn get_value(&self, row: usize, column: usize) -> Option<usize> {
+        let candidates = self.cells[row * self.get_side() + column];
+        if candidates.count_ones() == 1 {
+            Some(candidates.trailing_zeros() as usize)
+        } else {
+            None
+        }
+    }
+
+    /// The grid in the one line format accepted by `parse`.
+    pub fn to_line(&self) -> String {
+        (0..self.cells.len())
+            .map(|cell| self.symbol(cell))
+            .collect()
+    }
+
+    fn symbol(&self, cell: usize) -> char {
+        let side = self.get_side();
+        match self.get_value(cell / side, cell % side) {
+            Some(value) => SYMBOLS.chars().nth(value).unwrap(),
+            None => '.',
+        }
+    }
+
+    // Rows, columns and boxes, each a list of cells.
+    fn units(&self) -> Vec<Vec<usize>> {
+        let (size, side) = (self.size, self.get_side());
+        let mut result = vec![];
+        for i in 0..side {
+            result.push((0..side).map(|j| i * side + j).collect());
+            result.push((0..side).map(|j| j * side + i).collect());
+            let (top, left) = (i / size * size, i % size * size);
+            result.push(
+                (0..side)
+                    .map(|j| (top + j / size) * side + left + j % size)
+                    .collect(),
+            );
+        }
+        result
+    }
+}
+
+impl Display for Sudoku {
+    fn fmt(&self, dest: &mut fmt::Formatter) -> fmt::Result {
+        let (size, side) = (self.size, self.get_side());
+        for row in 0..side {
+            if row > 0 && row % size == 0 {
+                writeln!(dest)?;
+            }
+            for column in 0..side {
+                if column > 0 && column % size == 0 {
+                    write!(dest, " ")?;
+                }
+                write!(dest, "{}", self.symbol(row * side + column))?;
+            }
+            writeln!(dest)?;
+        }
+        Ok(())
+    }
+}
+
+impl State for Sudoku {
+    fn is_solution(&self) -> bool {
+        self.cells.iter().all(|c| c.count_ones() == 1)
+    }
+
+    fn branch(&self) -> Vec<Self> {
+        self.decisions()
+            .iter()
+            .map(|d| {
+                let mut s = self.clone();
+                s.decide(d);
+                s
+            })
+            .collect()
+    }
+
+    fn propagate(&mut self) -> Result<Changed, Conflict> {
+        let mut change_counter = 0;
+        if !self.apply_naked_singles(&mut change_counter)
+            || !self.apply_hidden_singles(&mut change_counter)
+        {
+            return Err(Conflict);
+        }
+        Ok(Changed::from_count(change_counter))
+    }
+}
+
+impl Decide for Sudoku {
+    /// Cell and the bit of its value.
+    type Decision = (usize, u64);
+
+    fn decisions(&self) -> Vec<Self::Decision> {
+        // the cell with the fewest candidates
+        let cell = match (0..self.cells.len())
+            .filter(|c| self.cells[*c].count_ones() > 1)
+            .min_by_key(|c| self.cells[*c].count_ones())
+        {
+            Some(cell) => cell,
+            None => return vec![],
+        };
+        let mut result = vec![];
+        let mut candidates = self.cells[cell];
+        while candidates != 0 {
+            let bit = candidates & candidates.wrapping_neg();
+            candidates ^= bit;
+            result.push((cell, bit));
+        }
+        result
+    }
+
+    fn domain(&self, &(cell, _): &Self::Decision) -> Vec<Self::Decision> {
+        (0..self.get_side())
+            .map(|value| (cell, 1 << value))
+            .collect()
+    }
+
+    fn decide(&mut self, &(cell, bit): &Self::Decision) {
+        self.cells[cell] = bit;
+    }
+
+    fn entails(&self, &(cell, bit): &Self::Decision) -> bool {
+        self.cells[cell] == bit
+    }
+}
+
+impl Sudoku {
+    // Naked singles: the value of a solved cell is removed from its units.
+    fn apply_naked_singles(&mut self, change_counter: &mut i32) -> bool {
+        for unit in self.units() {
+            for &cell in &unit {
+                let value = self.cells[cell];
+                if value.count_ones() != 1 {
+                    continue;
+                }
+                for &other in unit.iter().filter(|o| **o != cell) {
+                    if self.cells[other] & value != 0 {
+                        self.cells[other] &= !value;
+                        *change_counter += 1;
+                        if self.cells[other] == 0 {
+                            return false;
+                        }
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    // Hidden singles: a value possible in one cell of a unit only goes there.
+    fn apply_hidden_singles(&mut self, change_counter: &mut i32) -> bool {
+        for unit in self.units() {
+            for value in 0..self.get_side() {
+                let bit = 1 << value;
+                let mut cells = unit.iter().filter(|c| self.cells[**c] & bit != 0);
+                match (cells.next(), cells.next()) {
+                    (None, _) => return false,
+                    (Some(&cell), None) if self.cells[cell] != bit => {
+                        self.cells[cell] = bit;
+                        *change_counter += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::backtracking::{count_all, find_first};
+
+    #[test]
+    fn test_parse() {
+        let sudoku = Sudoku::parse("1... .2.. ..3. ...4").unwrap();
+        assert_eq!(2, sudoku.get_size());
+        assert_eq!(Some(3), sudoku.get_value(3, 3));
+        assert_eq!(None, sudoku.get_value(0, 1));
+        assert_eq!("1....2....3....4", sudoku.to_line());
+        assert!(Sudoku::parse("1234").is_none());
+        assert!(Sudoku::parse("5...............").is_none());
+    }
+
+    #[test]
+    fn test_apply_naked_singles() {
+        let mut sudoku = Sudoku::parse("1...............").unwrap();
+        let mut change_counter = 0;
+        assert!(sudoku.apply_naked_singles(&mut change_counter));
+        assert_eq!(0b1110, sudoku.cells[1]);
+        assert_eq!(0b1110, sudoku.cells[5]);
+        assert_eq!(0b1111, sudoku.cells[10]);
+    }
+
+    #[test]
+    fn test_solve() {
+        let puzzle =
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79";
+        let solution = find_first(Sudoku::parse(puzzle).unwrap()).unwrap();
+        assert_eq!(
+            "534678912672195348198342567859761423426853791713924856961537284287419635345286179",
+            solution.to_line()
+        );
+    }
+
+    #[test]
+    fn test_solve_hard() {
+        let puzzle =
+            "8..........36......7..9.2...5...7.......457.....1...3...1....68..85...1..9....4..";
+        let sudoku = Sudoku::parse(puzzle).unwrap();
+        assert_eq!(1, count_all(sudoku.clone()));
+        assert_eq!(
+            "812753649943682175675491283154237896369845721287169534521974368438526917796318452",
+            find_first(sudoku).unwrap().to_line()
+        );
+    }
+}
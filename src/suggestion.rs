@@ -0,0 +1,233 @@
+use crate::backtracking::find_all;
+use crate::reference::{check_fact, check_negative_fact, check_relation, owners};
+use crate::relation::Relation;
+use crate::zebra::{Fact, RelationClue, Zebra};
+
+/// A clue which may be added to a puzzle. Choices are `(property, choice)`
+/// pairs as in `ZebraProperties`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandidateClue {
+    /// Both choices belong to the same object (`ZebraBuilder::fact`).
+    Fact((usize, usize), (usize, usize)),
+    /// The choices belong to different objects (`ZebraBuilder::not_fact`).
+    NegativeFact((usize, usize), (usize, usize)),
+    /// The objects having the choices are next to each other
+    /// (`Relation::NextTo`).
+    NextTo((usize, usize), (usize, usize)),
+}
+
+#[derive(Debug)]
+pub struct Suggestion {
+    pub clue: CandidateClue,
+    /// Number of solutions in which the clue does not hold.
+    pub removed: usize,
+}
+
+impl CandidateClue {
+    pub fn describe(&self, zebra: &Zebra) -> String {
+        let props = zebra.get_properties();
+        let name = |(p, ch): (usize, usize)| {
+            format!(
+                "{}={}",
+                props.get_property_name(p),
+                props.get_property_choice_name_by_nr(p, ch)
+            )
+        };
+        match *self {
+            CandidateClue::Fact(c1, c2) => format!("fact: {}, {}", name(c1), name(c2)),
+            CandidateClue::NegativeFact(c1, c2) => {
+                format!("not fact: {}, {}", name(c1), name(c2))
+            }
+            CandidateClue::NextTo(c1, c2) => format!("next to: {}, {}", name(c1), name(c2)),
+        }
+    }
+
+    // Whether the clue holds in a solved puzzle, checked as
+    // `reference::check_clue` would once the clue is added to the puzzle.
+    fn holds(&self, solution: &Zebra, position: Option<usize>) -> bool {
+        let clue = solution.get_clue_count();
+        let fact = |choice1, choice2| Fact {
+            choice1,
+            choice2,
+            clue,
+        };
+        match *self {
+            CandidateClue::Fact(c1, c2) => check_fact(solution, &fact(c1, c2)).is_ok(),
+            CandidateClue::NegativeFact(c1, c2) => {
+                check_negative_fact(solution, &fact(c1, c2)).is_ok()
+            }
+            CandidateClue::NextTo(c1, c2) => {
+                let relation = RelationClue {
+                    relation: Relation::NextTo,
+                    property: position.expect("Adjacency clues need the position property"),
+                    choices: vec![c1, c2],
+                    negated: false,
+                    clue,
+                };
+                check_relation(solution, &relation).is_ok()
+            }
+        }
+    }
+}
+
+// Clues on the choices held by exactly one object in `target`, which name
+// it. Choices shared by several objects or by none would not.
+fn candidates(target: &Zebra, position: Option<usize>) -> Vec<CandidateClue> {
+    let (object_count, property_count) = {
+        let props = target.get_properties();
+        (props.get_object_count(), props.get_property_count())
+    };
+    let choice = |p, o| (p, target.get_determined(p, o).unwrap());
+    let mut result = vec![];
+    for p1 in 0..property_count {
+        for p2 in (p1 + 1)..property_count {
+            for o1 in 0..object_count {
+                for o2 in 0..object_count {
+                    let c1 = choice(p1, o1);
+                    let c2 = choice(p2, o2);
+                    if owners(target, c1).len() != 1 || owners(target, c2).len() != 1 {
+                        continue;
+                    }
+                    if o1 == o2 {
+                        result.push(CandidateClue::Fact(c1, c2));
+                    } else {
+                        result.push(CandidateClue::NegativeFact(c1, c2));
+                        let next_to = CandidateClue::NextTo(c1, c2);
+                        if position.is_some() && next_to.holds(target, position) {
+                            result.push(next_to);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Proposes clues which are true in `target` and which rule out other
+/// solutions of the puzzle.
+///
+/// `target` must be a solution of `zebra`. Suggestions are sorted by the
+/// number of removed solutions, the best first; clues which do not remove
+/// anything are left out. Adjacency clues are only proposed when the
+/// position property is given; nothing is proposed for an unknown one.
+pub fn suggest_clues(zebra: &Zebra, target: &Zebra, position: Option<&str>) -> Vec<Suggestion> {
+    let position = match position {
+        Some(name) => match zebra.get_properties().get_property(name) {
+            Some(p) => Some(p),
+            None => return vec![],
+        },
+        None => None,
+    };
+    let solutions = find_all(zebra.clone());
+    let mut result: Vec<Suggestion> = candidates(target, position)
+        .into_iter()
+        .map(|clue| Suggestion {
+            clue,
+            removed: solutions
+                .iter()
+                .filter(|s| !clue.holds(s, position))
+                .count(),
+        })
+        .filter(|s| s.removed > 0)
+        .collect();
+    result.sort_by_key(|s| std::cmp::Reverse(s.removed));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::zebra::{ZebraBuilder, NONE};
+
+    fn make_zebra<'a>() -> Zebra<'a> {
+        // The order of the p2 choices is free, 6 solutions.
+        ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("p1", vec!["p1a", "p1b", "p1c"])
+            .choice("p2", vec!["1", "2", "3"])
+            .build()
+    }
+
+    #[test]
+    fn test_suggest_clues() {
+        let zebra = make_zebra();
+        let target = find_all(zebra.clone()).remove(0);
+        let suggestions = suggest_clues(&zebra, &target, None);
+
+        // each fact keeps 2 of 6 solutions, each negative fact 4
+        assert_eq!(3 + 6, suggestions.len());
+        assert_eq!(4, suggestions[0].removed);
+        assert!(matches!(suggestions[0].clue, CandidateClue::Fact(_, _)));
+        assert_eq!(2, suggestions[8].removed);
+        assert_eq!("fact: p1=p1a, p2=1", suggestions[0].clue.describe(&zebra));
+    }
+
+    #[test]
+    fn test_suggest_next_to() {
+        let zebra = make_zebra();
+        let target = find_all(zebra.clone()).remove(0);
+        let suggestions = suggest_clues(&zebra, &target, Some("p2"));
+        let next_to: Vec<&Suggestion> = suggestions
+            .iter()
+            .filter(|s| matches!(s.clue, CandidateClue::NextTo(_, _)))
+            .collect();
+        // p1b next to 1 or 3 forces it to the middle
+        let removed: Vec<usize> = next_to.iter().map(|s| s.removed).collect();
+        assert_eq!(vec![4, 4, 2, 2], removed);
+    }
+
+    #[test]
+    fn test_suggest_unused_and_shared_choices() {
+        // a pet may go unused, colors may be shared and hats missing
+        let zebra = ZebraBuilder::new()
+            .set_object_count(2)
+            .choice("name", vec!["Ann", "Bob"])
+            .choice("pet", vec!["cat", "dog", "fox"])
+            .occurrence("pet", vec!["cat", "dog", "fox"], 0, 1)
+            .choice("color", vec!["red", "green"])
+            .occurrence("color", vec!["red", "green"], 0, 2)
+            .choice("hat", vec!["cap"])
+            .optional("hat")
+            .build();
+        let solutions = find_all(zebra.clone());
+        let target = solutions
+            .iter()
+            .find(|s| {
+                s.get_determined_name("Ann", "pet") == Some("cat")
+                    && s.get_determined_name("Ann", "color") == Some("red")
+                    && s.get_determined_name("Bob", "color") == Some("red")
+                    && s.get_determined_name("Bob", "hat") == Some("cap")
+            })
+            .unwrap();
+        let suggestions = suggest_clues(&zebra, target, None);
+        let texts: Vec<String> = suggestions
+            .iter()
+            .map(|s| s.clue.describe(&zebra))
+            .collect();
+
+        // red is shared, no object is left with green
+        assert!(texts.iter().all(|t| !t.contains("color=")));
+        let removed = |text: &str| {
+            suggestions
+                .iter()
+                .find(|s| s.clue.describe(&zebra) == text)
+                .map(|s| s.removed)
+        };
+        // the cat is Ann's and nobody else's
+        let without_cat = solutions
+            .iter()
+            .filter(|s| s.get_owner_name("pet", "cat") != Some("Ann"))
+            .count();
+        assert_eq!(Some(without_cat), removed("fact: name=Ann, pet=cat"));
+        // Ann has no hat, which does not keep others from lacking one
+        let hats = solutions
+            .iter()
+            .filter(|s| s.get_determined_name("Ann", "hat") != Some(NONE))
+            .count();
+        assert_eq!(Some(hats), removed("fact: name=Ann, hat=none"));
+
+        assert!(suggest_clues(&zebra, target, Some("place")).is_empty());
+    }
+}
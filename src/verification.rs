@@ -0,0 +1,227 @@
+use crate::reference::{check_clue, choice, choice_name, name, object_name, owners};
+use crate::zebra::Zebra;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+/// A rule of the puzzle broken by an assignment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// The broken clue. `None` for an object missing a choice, or for a
+    /// choice held by too many or too few objects when no clue declared it.
+    pub clue: Option<usize>,
+    pub explanation: String,
+}
+
+impl Display for Violation {
+    fn fmt(&self, dest: &mut fmt::Formatter) -> fmt::Result {
+        match self.clue {
+            Some(clue) => write!(dest, "clue {}: {}", clue, self.explanation),
+            None => write!(dest, "{}", self.explanation),
+        }
+    }
+}
+
+/// Builds an answer to `verify` from names, for example read from a file or
+/// given by an external solver: the choice of each property of each object.
+/// Objects and properties left out stay as in `zebra`. Returns the unknown
+/// names instead, sorted.
+pub fn answer_from_names<'a>(
+    zebra: &Zebra<'a>,
+    assignment: &HashMap<&str, HashMap<&str, &str>>,
+) -> Result<Zebra<'a>, Vec<String>> {
+    let mut result = zebra.clone();
+    let mut unknown = vec![];
+    let props = zebra.get_properties();
+    for (object, choices) in assignment {
+        let o = props.get_object(object);
+        if o.is_none() {
+            unknown.push(format!("unknown object {}", object));
+        }
+        for (property, choice) in choices {
+            let p = props.get_property(property);
+            let ch = p.and_then(|_| props.get_property_choice(property, choice));
+            match (o, p, ch) {
+                (_, None, _) => unknown.push(format!("unknown property {}", property)),
+                (_, _, None) => unknown.push(format!("unknown choice {}={}", property, choice)),
+                (Some(o), Some(p), Some(ch)) => result.determine_choice(p, o, ch),
+                (None, _, _) => {}
+            }
+        }
+    }
+    if unknown.is_empty() {
+        Ok(result)
+    } else {
+        unknown.sort();
+        unknown.dedup();
+        Err(unknown)
+    }
+}
+
+/// Checks a complete assignment, for example an answer entered by a user
+/// with `Zebra::set_determined_name` or built by `answer_from_names`,
+/// against every rule of the puzzle: each object has one choice of each
+/// property, each choice is held by the allowed number of objects (by one
+/// when the choices are all different), no two objects share a cell of a
+/// grid and every hard clue holds. Returns the broken rules, only the
+/// missing choices when there are any.
+pub fn verify(zebra: &Zebra) -> Vec<Violation> {
+    let props = zebra.get_properties();
+    let object_count = props.get_object_count();
+    let mut result = vec![];
+    for o in 0..object_count {
+        for p in 0..props.get_property_count() {
+            if zebra.get_determined(p, o).is_none() {
+                result.push(Violation {
+                    clue: None,
+                    explanation: format!(
+                        "{} has no single {}",
+                        object_name(zebra, o),
+                        props.get_property_name(p)
+                    ),
+                });
+            }
+        }
+    }
+    if !result.is_empty() {
+        return result;
+    }
+
+    for p in 0..props.get_property_count() {
+        for ch in 0..props.get_choice_count(p) {
+            if zebra.is_choice_exempt(p, ch) {
+                continue;
+            }
+            let owners = owners(zebra, (p, ch));
+            let (min, max) = props.get_occurrence(p, ch);
+            if owners.len() < min || owners.len() > max {
+                let names: Vec<&str> = owners.iter().map(|o| object_name(zebra, *o)).collect();
+                let expected = if min == max {
+                    min.to_string()
+                } else {
+                    format!("{} to {}", min, max)
+                };
+                result.push(Violation {
+                    clue: props.get_declaration(p, ch),
+                    explanation: format!(
+                        "{} is held by {} objects ({}), should be {}",
+                        name(zebra, (p, ch)),
+                        owners.len(),
+                        names.join(", "),
+                        expected
+                    ),
+                });
+            }
+        }
+    }
+    for &(rows, columns) in &props.grids {
+        let cell = |o: usize| (choice(zebra, rows, o), choice(zebra, columns, o));
+        for o1 in 0..object_count {
+            for o2 in (o1 + 1..object_count).filter(|o2| cell(o1) == cell(*o2)) {
+                result.push(Violation {
+                    clue: None,
+                    explanation: format!(
+                        "{} and {} share the cell {}, {}",
+                        object_name(zebra, o1),
+                        object_name(zebra, o2),
+                        choice_name(zebra, rows, o1),
+                        choice_name(zebra, columns, o1)
+                    ),
+                });
+            }
+        }
+    }
+    for clue in (0..zebra.get_clue_count()).filter(|c| zebra.is_clue_enforced(*c)) {
+        if let Err(explanation) = check_clue(zebra, clue) {
+            result.push(Violation {
+                clue: Some(clue),
+                explanation,
+            });
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::backtracking::find_first;
+    use crate::myzebra::init_my_zebra;
+
+    #[test]
+    fn test_verify() {
+        let zebra = init_my_zebra();
+        let solution = find_first(zebra.clone()).unwrap();
+        assert!(verify(&solution).is_empty());
+
+        // the red house and the second one swap their colors
+        let mut answer = solution.clone();
+        let red = solution.get_owner_name("color", "red").unwrap();
+        let other = solution.get_determined_name("2", "color").unwrap();
+        answer.set_determined_name(red, "color", other);
+        answer.set_determined_name("2", "color", "red");
+        let violations = verify(&answer);
+        assert!(!violations.is_empty());
+        assert!(violations.iter().all(|v| v.clue.is_some()));
+        assert!(violations.iter().any(|v| v
+            .explanation
+            .contains("has nationality=Englishman but color=")));
+
+        // two houses painted red
+        let mut answer = solution.clone();
+        answer.set_determined_name("2", "color", "red");
+        let violations = verify(&answer);
+        assert!(violations
+            .iter()
+            .any(|v| v.explanation.contains("color=red is held by 2 objects")));
+
+        let mut answer = zebra.clone();
+        answer.set_determined_name("2", "pet", "zebra");
+        let violations = verify(&answer);
+        assert!(violations
+            .iter()
+            .any(|v| v.to_string() == "1 has no single color"));
+    }
+
+    #[test]
+    fn test_answer_from_names() {
+        let zebra = init_my_zebra();
+        let houses = [
+            ("1", ["Norwegian", "yellow", "watter", "Kools", "fox"]),
+            ("2", ["Ukrainian", "blue", "tea", "Chesterfields", "horse"]),
+            ("3", ["Englishman", "red", "milk", "OldGold", "snail"]),
+            ("4", ["Spaniard", "ivory", "juice", "LuckyStrike", "dog"]),
+            ("5", ["Japanese", "green", "coffee", "Parliaments", "zebra"]),
+        ];
+        let properties = ["nationality", "color", "beverage", "smoke", "pet"];
+        let mut assignment: HashMap<&str, HashMap<&str, &str>> = houses
+            .iter()
+            .map(|(house, choices)| (*house, properties.iter().copied().zip(*choices).collect()))
+            .collect();
+        let answer = answer_from_names(&zebra, &assignment).unwrap();
+        assert!(verify(&answer).is_empty());
+
+        // the zebra and the dog swapped
+        assignment.get_mut("4").unwrap().insert("pet", "zebra");
+        assignment.get_mut("5").unwrap().insert("pet", "dog");
+        let answer = answer_from_names(&zebra, &assignment).unwrap();
+        let violations = verify(&answer);
+        assert_eq!(1, violations.len());
+        assert_eq!(
+            "clue 2: 4 has nationality=Spaniard but pet=zebra",
+            violations[0].to_string()
+        );
+
+        assignment.get_mut("5").unwrap().insert("hat", "cap");
+        assignment.get_mut("4").unwrap().insert("pet", "cat");
+        assignment.insert("6", HashMap::new());
+        assert_eq!(
+            Err(vec![
+                "unknown choice pet=cat".to_string(),
+                "unknown object 6".to_string(),
+                "unknown property hat".to_string()
+            ]),
+            answer_from_names(&zebra, &assignment).map(|_| ())
+        );
+    }
+}
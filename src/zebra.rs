@@ -1,22 +1,137 @@
-use crate::backtracking::State;
+use crate::backtracking::{Changed, Conflict, State};
+use crate::expression::{apply_arithmetic, ArithmeticClue, Expr, Selector, Target, Term};
+use crate::learning::Decide;
+use crate::literal::Literal;
+use crate::relation::{Arrangement, Comparison, GridRelation, Relation};
 use bitvec::prelude::*;
-use std::cell::RefCell;
+use std::cell::{Ref, RefCell};
 use std::clone::Clone;
+use std::cmp::Ordering;
 use std::fmt::{self, Debug, Display};
 use std::rc::Rc;
 
-#[derive(Debug)]
+pub type PredicateTest = Box<dyn Fn(&str, &str) -> bool>;
+
+/// Name of the choice of an optional property held by objects without any
+/// of the other choices, see `ZebraBuilder::optional`.
+pub const NONE: &str = "none";
+
+#[derive(Debug, Clone)]
 pub struct Fact {
-    choice1: (usize, usize),
-    choice2: (usize, usize),
+    pub(crate) choice1: (usize, usize),
+    pub(crate) choice2: (usize, usize),
+    pub(crate) clue: usize,
 }
 
 pub struct Predicate {
-    choice1: (usize, usize),
-    choice2: (usize, usize),
-    property3: usize,
-    property4: usize,
-    test: Box<dyn Fn(&str, &str) -> bool>,
+    pub(crate) choice1: (usize, usize),
+    pub(crate) choice2: (usize, usize),
+    pub(crate) property3: usize,
+    pub(crate) property4: usize,
+    pub(crate) test: PredicateTest,
+    pub(crate) clue: usize,
+}
+
+/// Objects having `choices` are in `relation` by their places in `property`.
+#[derive(Debug, Clone)]
+pub struct RelationClue {
+    pub(crate) relation: Relation,
+    pub(crate) property: usize,
+    pub(crate) choices: Vec<(usize, usize)>,
+    pub(crate) negated: bool,
+    pub(crate) clue: usize,
+}
+
+/// The values of the objects having the choices in `property` compare.
+#[derive(Debug)]
+pub struct ComparisonClue {
+    pub(crate) comparison: Comparison,
+    pub(crate) property: usize,
+    pub(crate) choice1: (usize, usize),
+    pub(crate) choice2: (usize, usize),
+    pub(crate) clue: usize,
+}
+
+/// Objects having the choices are in `relation` by their cells, given by the
+/// choices of the `rows` and `columns` properties.
+#[derive(Debug)]
+pub struct GridClue {
+    pub(crate) relation: GridRelation,
+    pub(crate) rows: usize,
+    pub(crate) columns: usize,
+    pub(crate) choice1: (usize, usize),
+    pub(crate) choice2: (usize, usize),
+    pub(crate) clue: usize,
+}
+
+/// A `Literal` resolved to choice numbers. The clue is the one of the compound
+/// clue using the literal.
+#[derive(Debug, Clone)]
+pub(crate) enum Condition {
+    Same(Fact),
+    Different(Fact),
+    Relation(RelationClue),
+}
+
+/// At least one of the conditions holds, exactly one if `exclusive`.
+#[derive(Debug)]
+pub struct Disjunction {
+    pub(crate) conditions: Vec<Condition>,
+    pub(crate) exclusive: bool,
+    pub(crate) clue: usize,
+}
+
+/// Selects objects having, for each listed property, one of the listed
+/// choices.
+#[derive(Debug, Clone)]
+pub(crate) struct ObjectFilter(pub(crate) Vec<(usize, Vec<usize>)>);
+
+#[derive(Debug)]
+pub(crate) enum Counted {
+    Conditions(Vec<Condition>),
+    Objects(ObjectFilter),
+}
+
+/// Between `min` and `max` of the counted conditions (or objects) hold.
+#[derive(Debug)]
+pub struct Cardinality {
+    pub(crate) counted: Counted,
+    pub(crate) min: usize,
+    pub(crate) max: usize,
+    pub(crate) clue: usize,
+}
+
+/// If the condition holds, the consequence holds too.
+#[derive(Debug)]
+pub struct Implication {
+    pub(crate) condition: Condition,
+    pub(crate) consequence: Condition,
+    pub(crate) clue: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClueKind {
+    Choice,
+    Fact,
+    NegativeFact,
+    Predicate,
+    Relation,
+    Disjunction,
+    Implication,
+    Cardinality,
+    Comparison,
+    Arithmetic,
+    GridRelation,
+}
+
+/// A single builder call as written by the puzzle author. Clues are
+/// numbered in the order they were given to `ZebraBuilder`.
+#[derive(Debug)]
+pub struct Clue {
+    kind: ClueKind,
+    text: String,
+    // Penalty for violating a soft clue, `None` for the hard ones.
+    weight: Option<i64>,
 }
 
 #[derive(Debug)]
@@ -24,8 +139,35 @@ pub struct ZebraProperties<'a> {
     object_count: usize,
     properties: Vec<&'a str>,
     options: Vec<Vec<&'a str>>,
-    facts: Vec<Fact>,
-    predicates: Vec<Predicate>,
+    // Choice clue which declared each option, if any.
+    declarations: Vec<Vec<Option<usize>>>,
+    // Lowest and highest number of objects having each option.
+    occurrences: Vec<Vec<(usize, usize)>>,
+    // Start of each property in `Zebra::values`, filled in by
+    // `ZebraBuilder::build`.
+    offsets: Vec<usize>,
+    // Property whose choices name the objects.
+    identity: usize,
+    // Properties whose `NONE` choice any number of objects may have, its
+    // occurrence is filled in by `ZebraBuilder::build`.
+    optional: Vec<usize>,
+    // Row and column properties of each grid.
+    pub(crate) grids: Vec<(usize, usize)>,
+    numeric: Vec<bool>,
+    arrangements: Vec<Arrangement>,
+    // Value of each option, filled in by `ZebraBuilder::build`.
+    numbers: Vec<Vec<i64>>,
+    pub(crate) facts: Vec<Fact>,
+    pub(crate) negative_facts: Vec<Fact>,
+    pub(crate) predicates: Vec<Predicate>,
+    pub(crate) relations: Vec<RelationClue>,
+    pub(crate) disjunctions: Vec<Disjunction>,
+    pub(crate) implications: Vec<Implication>,
+    pub(crate) cardinalities: Vec<Cardinality>,
+    pub(crate) comparisons: Vec<ComparisonClue>,
+    pub(crate) arithmetic: Vec<ArithmeticClue>,
+    pub(crate) grid_relations: Vec<GridClue>,
+    clues: Vec<Clue>,
 }
 
 impl Debug for Predicate {
@@ -38,10 +180,45 @@ impl Debug for Predicate {
     }
 }
 
+impl Condition {
+    fn negate(&self) -> Condition {
+        match self {
+            Condition::Same(f) => Condition::Different(f.clone()),
+            Condition::Different(f) => Condition::Same(f.clone()),
+            Condition::Relation(r) => Condition::Relation(RelationClue {
+                negated: !r.negated,
+                ..r.clone()
+            }),
+        }
+    }
+}
+
+impl Clue {
+    pub fn get_kind(&self) -> ClueKind {
+        self.kind
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn get_weight(&self) -> Option<i64> {
+        self.weight
+    }
+}
+
+impl Display for Clue {
+    fn fmt(&self, dest: &mut fmt::Formatter) -> fmt::Result {
+        write!(dest, "{}", self.text)
+    }
+}
+
 #[derive(Debug)]
 pub struct Zebra<'a> {
     props: Rc<RefCell<ZebraProperties<'a>>>,
     values: BitVec,
+    // Clues propagated in this state, one bit per clue.
+    enabled_clues: BitVec,
 }
 
 impl<'a> Clone for Zebra<'a> {
@@ -49,6 +226,7 @@ impl<'a> Clone for Zebra<'a> {
         Zebra {
             props: Rc::clone(&self.props),
             values: self.values.clone(),
+            enabled_clues: self.enabled_clues.clone(),
         }
     }
 }
@@ -69,36 +247,82 @@ impl Zebra<'_> {
         self.values.set(ix, value);
     }
 
-    fn index(&self, property: usize, object: usize, choice: usize) -> usize {
-        let oc = self.props.borrow().get_object_count();
-        property * oc * oc + object * oc + choice
+    pub(crate) fn index(&self, property: usize, object: usize, choice: usize) -> usize {
+        let props = self.props.borrow();
+        props.offsets[property] + object * props.get_choice_count(property) + choice
     }
 
-    pub fn fix_first_property(&mut self) {
-        // First object will be assigned the first choice, second object second ...
-        let property = 0;
+    fn choice_count(&self, property: usize) -> usize {
+        self.props.borrow().get_choice_count(property)
+    }
+
+    /// Gives object i the choice i of the identity property, its name.
+    pub fn fix_identity(&mut self) {
+        let property = self.props.borrow().get_identity();
         let cnt = self.props.borrow().get_object_count();
         for object in 0..cnt {
             self.determine_choice(property, object, object);
         }
     }
 
-    fn determine_choice(&mut self, property: usize, object: usize, choice: usize) {
-        let cnt = self.props.borrow().get_object_count();
-        for ch in 0..cnt {
+    #[deprecated(note = "use `fix_identity`, the identity is not always the first property")]
+    pub fn fix_first_property(&mut self) {
+        self.fix_identity();
+    }
+
+    // Breaks the symmetries of a new puzzle. The objects are interchangeable,
+    // which naming them by the identity property breaks on a line. Around a
+    // table the rotations are symmetric too, see `fix_rotation`.
+    fn break_symmetry(&mut self) {
+        let circle = {
+            let props = self.props.borrow();
+            (0..props.get_property_count())
+                .find(|p| props.get_arrangement(*p) == Arrangement::Circle)
+        };
+        match circle {
+            Some(circle) => self.fix_rotation(circle),
+            None => self.fix_identity(),
+        }
+    }
+
+    // Rotating everyone around the table gives an equivalent solution, so
+    // besides naming the objects, the first choice of the first other
+    // property is seated at the first seat.
+    fn fix_rotation(&mut self, circle: usize) {
+        self.fix_identity();
+        let props = self.props.clone();
+        let props = props.borrow();
+        let identity = props.get_identity();
+        let other = match (0..props.get_property_count()).find(|p| *p != circle) {
+            Some(other) => other,
+            None => return,
+        };
+        // the first object already has the first choice of the identity
+        let property = if circle == identity { other } else { circle };
+        if self.is_choice_exempt(property, 0) || props.get_occurrence(property, 0) != (1, 1) {
+            return;
+        }
+        self.determine_choice(property, 0, 0);
+        for object in 1..props.get_object_count() {
+            self.set_choice_enabled(property, object, 0, false);
+        }
+    }
+
+    pub fn determine_choice(&mut self, property: usize, object: usize, choice: usize) {
+        for ch in 0..self.choice_count(property) {
             self.set_choice_enabled(property, object, ch, choice == ch)
         }
     }
 
     fn is_determined(&self, property: usize, object: usize) -> bool {
-        ((0..self.props.borrow().get_object_count())
+        ((0..self.choice_count(property))
             .filter(|choice| self.is_choice_enabled(property, object, *choice)))
         .count()
             == 1
     }
 
     fn find_determined(&self, property: usize, object: usize) -> usize {
-        ((0..self.props.borrow().get_object_count())
+        ((0..self.choice_count(property))
             .find(|choice| self.is_choice_enabled(property, object, *choice)))
         .unwrap()
     }
@@ -123,302 +347,1830 @@ impl Zebra<'_> {
                 *change_counter += 1;
             }
         }
-        return true;
+        true
     }
-}
 
-impl Display for Zebra<'_> {
-    fn fmt(&self, dest: &mut fmt::Formatter) -> fmt::Result {
-        for property in 0..self.props.borrow().get_property_count() {
-            write!(
-                dest,
-                "{} -----\n",
-                self.props.borrow().get_property_name(property)
-            )?;
-            for object in 0..self.props.borrow().get_object_count() {
-                write!(dest, "{}:", object)?;
-                for choice in 0..self.props.borrow().get_object_count() {
-                    if self.is_choice_enabled(property, object, choice) {
-                        write!(
-                            dest,
-                            "{} ",
-                            self.props
-                                .borrow()
-                                .get_property_choice_name_by_nr(property, choice)
-                        )?;
+    fn apply_half_negative_fact(
+        &mut self,
+        object: usize,
+        choice1: (usize, usize),
+        choice2: (usize, usize),
+        change_counter: &mut i32,
+    ) -> bool {
+        // an object having choice1 cannot have choice2
+        let (p1, ch1) = choice1;
+        let (p2, ch2) = choice2;
+        if self.is_determined(p1, object)
+            && self.is_choice_enabled(p1, object, ch1)
+            && self.is_choice_enabled(p2, object, ch2)
+        {
+            if self.is_determined(p2, object) {
+                return false;
+            }
+            self.set_choice_enabled(p2, object, ch2, false);
+            *change_counter += 1;
+        }
+        true
+    }
+
+    fn apply_fact(&mut self, f: &Fact, change_counter: &mut i32) -> bool {
+        let property_count = self.props.borrow().get_property_count();
+        let object_count = self.props.borrow().get_object_count();
+        let implications = self.implied_choices(f.choice1, f.choice2);
+        for property in 0..property_count {
+            for object in 0..object_count {
+                if self.is_determined(property, object) {
+                    for &(choice1, choice2) in &implications {
+                        if !self.apply_half_fact(property, object, choice1, choice2, change_counter)
+                        {
+                            return false;
+                        }
                     }
                 }
-                write!(dest, "\n")?;
             }
         }
+        true
+    }
 
-        Ok(())
+    // Pairs of choices in which the first implies the second when the objects
+    // having `choice1` and `choice2` are the same ones, see
+    // `ZebraBuilder::fact`. A choice shared by more objects does not imply
+    // the other one whichever comes first: several houses may be red, only
+    // one of them the Englishman's.
+    pub(crate) fn implied_choices(
+        &self,
+        choice1: (usize, usize),
+        choice2: (usize, usize),
+    ) -> Vec<((usize, usize), (usize, usize))> {
+        let props = self.props.borrow();
+        let (_, max1) = props.get_occurrence(choice1.0, choice1.1);
+        let (_, max2) = props.get_occurrence(choice2.0, choice2.1);
+        match max1.cmp(&max2) {
+            Ordering::Less => vec![(choice1, choice2)],
+            Ordering::Equal => vec![(choice1, choice2), (choice2, choice1)],
+            Ordering::Greater => vec![(choice2, choice1)],
+        }
     }
-}
 
-impl State for Zebra<'_> {
-    fn is_solution(&self) -> bool {
-        for object in 0..self.props.borrow().get_object_count() {
-            for property in 0..self.props.borrow().get_property_count() {
-                if !self.is_determined(object, property) {
-                    return false;
-                }
+    fn apply_negative_fact(&mut self, f: &Fact, change_counter: &mut i32) -> bool {
+        let object_count = self.props.borrow().get_object_count();
+        for object in 0..object_count {
+            if !self.apply_half_negative_fact(object, f.choice1, f.choice2, change_counter)
+                || !self.apply_half_negative_fact(object, f.choice2, f.choice1, change_counter)
+            {
+                return false;
             }
         }
         true
     }
-    fn split(&self) -> Vec<Self> {
-        for property in 0..self.props.borrow().get_property_count() {
-            for object in 0..self.props.borrow().get_object_count() {
-                if !self.is_determined(property, object) {
-                    let mut result = vec![];
-                    for choice in 0..self.props.borrow().get_object_count() {
-                        if self.is_choice_enabled(property, object, choice) {
-                            let mut z = self.clone();
-                            z.determine_choice(property, object, choice);
-                            result.push(z);
-                        }
+
+    fn apply_predicate(&mut self, pred: &Predicate) -> bool {
+        let props2 = self.props.clone();
+        let (p1, ch1) = pred.choice1;
+        let (p2, ch2) = pred.choice2;
+        for object1 in 0..props2.borrow().get_object_count() {
+            for object2 in 0..props2.borrow().get_object_count() {
+                if self.is_determined(p1, object1)
+                    && self.is_determined(p2, object2)
+                    && self.is_choice_enabled(p1, object1, ch1)
+                    && self.is_choice_enabled(p2, object2, ch2)
+                    && self.is_determined(pred.property3, object1)
+                    && self.is_determined(pred.property4, object2)
+                {
+                    let ch3 = self.find_determined(pred.property3, object1);
+
+                    let ch4 = self.find_determined(pred.property4, object2);
+                    let s1 = props2
+                        .borrow()
+                        .get_property_choice_name_by_nr(pred.property3, ch3);
+                    let s2 = props2
+                        .borrow()
+                        .get_property_choice_name_by_nr(pred.property4, ch4);
+                    if !(pred.test)(s1, s2) {
+                        return false;
                     }
-                    return result;
                 }
             }
         }
-        vec![]
+        true
     }
 
-    fn apply_facts(&mut self, change_counter: &mut i32) -> bool {
+    /// Propagates a single clue, ignoring whether it is enabled. Returns
+    /// false when the clue contradicts the state.
+    pub fn apply_clue(&mut self, clue: usize, change_counter: &mut i32) -> bool {
         let props2 = self.props.clone();
-        for f in &props2.borrow().facts {
-            for property in 0..props2.borrow().get_property_count() {
-                for object in 0..props2.borrow().get_object_count() {
-                    if self.is_determined(property, object) {
-                        if !self.apply_half_fact(
-                            property,
-                            object,
-                            f.choice1,
-                            f.choice2,
-                            change_counter,
-                        ) {
-                            return false;
-                        }
-                        if !self.apply_half_fact(
-                            property,
-                            object,
-                            f.choice2,
-                            f.choice1,
-                            change_counter,
-                        ) {
-                            return false;
-                        }
+        let props = props2.borrow();
+        if let Some(f) = props.facts.iter().find(|f| f.clue == clue) {
+            return self.apply_fact(f, change_counter);
+        }
+        if let Some(f) = props.negative_facts.iter().find(|f| f.clue == clue) {
+            return self.apply_negative_fact(f, change_counter);
+        }
+        if let Some(pred) = props.predicates.iter().find(|p| p.clue == clue) {
+            return self.apply_predicate(pred);
+        }
+        if let Some(r) = props.relations.iter().find(|r| r.clue == clue) {
+            return self.apply_relation(r, change_counter);
+        }
+        if let Some(d) = props.disjunctions.iter().find(|d| d.clue == clue) {
+            return self.apply_disjunction(d, change_counter);
+        }
+        if let Some(i) = props.implications.iter().find(|i| i.clue == clue) {
+            return self.apply_implication(i, change_counter);
+        }
+        if let Some(c) = props.cardinalities.iter().find(|c| c.clue == clue) {
+            return self.apply_cardinality(c, change_counter);
+        }
+        if let Some(c) = props.comparisons.iter().find(|c| c.clue == clue) {
+            return self.apply_comparison(c, change_counter);
+        }
+        if let Some(c) = props.arithmetic.iter().find(|c| c.clue == clue) {
+            return apply_arithmetic(self, c, change_counter);
+        }
+        if let Some(g) = props.grid_relations.iter().find(|g| g.clue == clue) {
+            return self.apply_grid_relation(g, change_counter);
+        }
+        true
+    }
+
+    // Both choices belong to the same object.
+    fn apply_same(
+        &mut self,
+        choice1: (usize, usize),
+        choice2: (usize, usize),
+        change_counter: &mut i32,
+    ) -> bool {
+        let object_count = self.props.borrow().get_object_count();
+        let implications = self.implied_choices(choice1, choice2);
+        for object in 0..object_count {
+            for &((p1, ch1), (p2, ch2)) in &implications {
+                if self.is_choice_enabled(p1, object, ch1)
+                    && !self.is_choice_enabled(p2, object, ch2)
+                {
+                    if self.is_determined(p1, object) {
+                        return false;
                     }
+                    self.set_choice_enabled(p1, object, ch1, false);
+                    *change_counter += 1;
+                }
+                if self.is_determined(p1, object)
+                    && self.is_choice_enabled(p1, object, ch1)
+                    && !self.is_determined(p2, object)
+                {
+                    self.determine_choice(p2, object, ch2);
+                    *change_counter += 1;
                 }
             }
         }
         true
     }
 
-    fn apply_predicates(&mut self) -> bool {
-        let props2 = self.props.clone();
-        for pred in &props2.borrow().predicates {
-            let (p1, ch1) = pred.choice1;
-            let (p2, ch2) = pred.choice2;
-            for object1 in 0..props2.borrow().get_object_count() {
-                for object2 in 0..props2.borrow().get_object_count() {
-                    if self.is_determined(p1, object1)
-                        && self.is_determined(p2, object2)
-                        && self.is_choice_enabled(p1, object1, ch1)
-                        && self.is_choice_enabled(p2, object2, ch2)
-                        && self.is_determined(pred.property3, object1)
-                        && self.is_determined(pred.property4, object2)
+    // Returns `Some(true)` when the condition must hold, `Some(false)` when it
+    // cannot hold and `None` when it is not decided yet.
+    fn condition_status(&self, condition: &Condition) -> Option<bool> {
+        let object_count = self.props.borrow().get_object_count();
+        match condition {
+            Condition::Same(f) | Condition::Different(f) => {
+                let (p1, ch1) = f.choice1;
+                let (p2, ch2) = f.choice2;
+                let both = |o: usize| {
+                    self.is_choice_enabled(p1, o, ch1) && self.is_choice_enabled(p2, o, ch2)
+                };
+                let holds =
+                    |o: usize| both(o) && self.is_determined(p1, o) && self.is_determined(p2, o);
+                // a shared choice implying the other one must be known for
+                // all of its objects
+                let implied =
+                    self.implied_choices(f.choice1, f.choice2)
+                        .into_iter()
+                        .all(|((p, ch), _)| {
+                            self.props.borrow().get_occurrence(p, ch).1 <= 1
+                                || (0..object_count)
+                                    .all(|o| !self.is_choice_enabled(p, o, ch) || holds(o))
+                        });
+                let known = (0..object_count)
+                    .all(|o| self.is_determined(p1, o) && self.is_determined(p2, o));
+                let status = if !(0..object_count).any(both) {
+                    Some(false)
+                } else if (0..object_count).any(holds) && implied {
+                    Some(true)
+                } else if known {
+                    Some(false)
+                } else {
+                    None
+                };
+                match condition {
+                    Condition::Same(_) => status,
+                    _ => status.map(|s| !s),
+                }
+            }
+            Condition::Relation(r) => {
+                if !self.find_relation_support(r, None, &mut vec![]) {
+                    Some(false)
+                } else if let Condition::Relation(n) = condition.negate() {
+                    if !self.find_relation_support(&n, None, &mut vec![])
+                        && self.are_relation_choices_owned(r)
                     {
-                        let ch3 = self.find_determined(pred.property3, object1);
-
-                        let ch4 = self.find_determined(pred.property4, object2);
-                        let s1 = props2
-                            .borrow()
-                            .get_property_choice_name_by_nr(pred.property3, ch3);
-                        let s2 = props2
-                            .borrow()
-                            .get_property_choice_name_by_nr(pred.property4, ch4);
-                        if !(pred.test)(s1, s2) {
-                            return false;
-                        }
+                        Some(true)
+                    } else if self.is_relation_known(r) {
+                        // some objects with shared choices may be out of it
+                        Some(self.is_relation_supported(r))
+                    } else {
+                        None
                     }
+                } else {
+                    unreachable!()
                 }
             }
         }
-        true
     }
 
-    fn apply_permutations(&mut self, change_counter: &mut i32) -> bool {
-        let props2 = self.props.clone();
-        for property in 0..props2.borrow().get_property_count() {
-            for object in 0..props2.borrow().get_object_count() {
-                if self.is_determined(property, object) {
-                    let choice = self.find_determined(property, object);
-                    for object2 in 0..props2.borrow().get_object_count() {
-                        if object != object2 && self.is_choice_enabled(property, object2, choice) {
-                            *change_counter += 1;
-                            self.set_choice_enabled(property, object2, choice, false);
-                        }
+    // Whether each choice of the relation is sure to have an object, which
+    // a choice allowed to no object needs a determined one for.
+    fn are_relation_choices_owned(&self, r: &RelationClue) -> bool {
+        let object_count = self.props.borrow().get_object_count();
+        r.choices.iter().all(|&(p, ch)| {
+            self.props.borrow().get_occurrence(p, ch).0 > 0
+                || (0..object_count)
+                    .any(|o| self.is_determined(p, o) && self.is_choice_enabled(p, o, ch))
+        })
+    }
+
+    // Whether the places and the choices of the relation are determined for
+    // all objects.
+    fn is_relation_known(&self, r: &RelationClue) -> bool {
+        let object_count = self.props.borrow().get_object_count();
+        (0..object_count).all(|o| {
+            self.is_determined(r.property, o)
+                && r.choices.iter().all(|(p, _)| self.is_determined(*p, o))
+        })
+    }
+
+    // Whether every object having one of the choices of the relation is in
+    // it, in a state where it is known.
+    fn is_relation_supported(&self, r: &RelationClue) -> bool {
+        let object_count = self.props.borrow().get_object_count();
+        r.choices.iter().enumerate().all(|(i, &(p, ch))| {
+            (0..object_count).all(|o| {
+                !self.is_choice_enabled(p, o, ch) || {
+                    let place = self.find_determined(r.property, o);
+                    self.find_relation_support(r, Some((i, o, place)), &mut vec![])
+                }
+            })
+        })
+    }
+
+    // Whether failing the condition is weaker than the opposite condition,
+    // as for choices shared by several objects or possibly owned by none:
+    // not all of the red houses need to be out of a relation for it to fail,
+    // and none needs to if there is no red house.
+    fn is_failing_weaker(&self, condition: &Condition) -> bool {
+        let props = self.props.borrow();
+        let shared = |(p, ch): (usize, usize)| props.get_occurrence(p, ch).1 > 1;
+        match condition {
+            Condition::Same(f) | Condition::Different(f) => shared(f.choice1) && shared(f.choice2),
+            Condition::Relation(r) => r.choices.iter().any(|&(p, ch)| {
+                let (min, max) = props.get_occurrence(p, ch);
+                min == 0 || max > 1
+            }),
+        }
+    }
+
+    // Makes the condition hold, or fail when `value` is false. Failing is
+    // left to `condition_status` when it is weaker than the opposite
+    // condition.
+    fn apply_condition(
+        &mut self,
+        condition: &Condition,
+        value: bool,
+        change_counter: &mut i32,
+    ) -> bool {
+        let different = matches!(condition, Condition::Different(_));
+        if value == different && self.is_failing_weaker(condition) {
+            return true;
+        }
+        let condition = if value {
+            condition.clone()
+        } else {
+            condition.negate()
+        };
+        match &condition {
+            Condition::Same(f) => self.apply_same(f.choice1, f.choice2, change_counter),
+            Condition::Different(f) => self.apply_negative_fact(f, change_counter),
+            Condition::Relation(r) => self.apply_relation(r, change_counter),
+        }
+    }
+
+    fn apply_disjunction(&mut self, d: &Disjunction, change_counter: &mut i32) -> bool {
+        let max = if d.exclusive { 1 } else { d.conditions.len() };
+        self.apply_counted_conditions(&d.conditions, 1, max, change_counter)
+    }
+
+    fn apply_counted_conditions(
+        &mut self,
+        conditions: &[Condition],
+        min: usize,
+        max: usize,
+        change_counter: &mut i32,
+    ) -> bool {
+        let statuses: Vec<Option<bool>> = conditions
+            .iter()
+            .map(|c| self.condition_status(c))
+            .collect();
+        match count_statuses(&statuses, min, max) {
+            None => false,
+            Some(None) => true,
+            Some(Some(value)) => {
+                for (c, status) in conditions.iter().zip(statuses) {
+                    if status.is_some() {
+                        continue;
+                    }
+                    if !self.apply_condition(c, value, change_counter) {
+                        return false;
                     }
                 }
+                true
             }
         }
-        true
     }
-}
 
-impl<'a> ZebraProperties<'a> {
-    pub fn get_object_count(&self) -> usize {
-        self.object_count
+    fn apply_cardinality(&mut self, c: &Cardinality, change_counter: &mut i32) -> bool {
+        let filter = match &c.counted {
+            Counted::Conditions(conditions) => {
+                return self.apply_counted_conditions(conditions, c.min, c.max, change_counter)
+            }
+            Counted::Objects(filter) => filter,
+        };
+        let object_count = self.props.borrow().get_object_count();
+        let statuses: Vec<Option<bool>> = (0..object_count)
+            .map(|o| self.filter_status(filter, o))
+            .collect();
+        match count_statuses(&statuses, c.min, c.max) {
+            None => false,
+            Some(None) => true,
+            Some(Some(value)) => (0..object_count).all(|o| {
+                statuses[o].is_some() || self.apply_filter(filter, o, value, change_counter)
+            }),
+        }
     }
 
-    pub fn get_property_count(&self) -> usize {
-        self.properties.len()
+    // Lowest and highest value the object having the choice may have.
+    fn value_bounds(&self, property: usize, choice: (usize, usize)) -> Option<(i64, i64)> {
+        let props2 = self.props.clone();
+        let props = props2.borrow();
+        let object_count = props.get_object_count();
+        let (p, ch) = choice;
+        let values = (0..object_count)
+            .filter(|o| self.is_choice_enabled(p, *o, ch))
+            .flat_map(|o| {
+                (0..props.get_choice_count(property))
+                    .filter(move |v| self.is_choice_enabled(property, o, *v))
+                    .map(|v| props.get_value(property, v))
+            });
+        values.fold(None, |acc, v| match acc {
+            None => Some((v, v)),
+            Some((low, high)) => Some((low.min(v), high.max(v))),
+        })
     }
 
-    pub fn get_property_name(&self, i: usize) -> &'a str {
-        &self.properties[i]
+    // The value of the only object which may have the choice, if determined.
+    fn determined_value(&self, property: usize, choice: (usize, usize)) -> Option<i64> {
+        let object_count = self.props.borrow().get_object_count();
+        let (p, ch) = choice;
+        let mut owners = (0..object_count).filter(|o| self.is_choice_enabled(p, *o, ch));
+        match (owners.next(), owners.next()) {
+            (Some(o), None) if self.is_determined(p, o) => self
+                .get_determined(property, o)
+                .map(|v| self.props.borrow().get_value(property, v)),
+            _ => None,
+        }
     }
 
-    pub fn get_property(&self, name: &str) -> Option<usize> {
-        self.properties.iter().position(|s| *s == name)
+    fn apply_comparison(&mut self, c: &ComparisonClue, change_counter: &mut i32) -> bool {
+        let props2 = self.props.clone();
+        let object_count = props2.borrow().get_object_count();
+        let sides = [
+            (c.choice1, c.choice2, c.comparison),
+            (c.choice2, c.choice1, c.comparison.mirror()),
+        ];
+        for (first, second, comparison) in sides {
+            let (low, high) = match self.value_bounds(c.property, second) {
+                Some(bounds) => bounds,
+                None => return false,
+            };
+            let (min, max) = comparison.bounds(low, high);
+            let (p, ch) = first;
+            for object in 0..object_count {
+                if !self.is_choice_enabled(p, object, ch) {
+                    continue;
+                }
+                let allowed = |v: usize| {
+                    let value = props2.borrow().get_value(c.property, v);
+                    min <= value && value <= max
+                };
+                let enabled: Vec<usize> = (0..self.choice_count(c.property))
+                    .filter(|v| self.is_choice_enabled(c.property, object, *v))
+                    .collect();
+                if !enabled.iter().any(|v| allowed(*v)) {
+                    // the object cannot be the first one
+                    if self.is_determined(p, object) {
+                        return false;
+                    }
+                    self.set_choice_enabled(p, object, ch, false);
+                    *change_counter += 1;
+                } else if self.is_determined(p, object) {
+                    for v in enabled.into_iter().filter(|v| !allowed(*v)) {
+                        self.set_choice_enabled(c.property, object, v, false);
+                        *change_counter += 1;
+                    }
+                }
+            }
+        }
+        match (
+            self.determined_value(c.property, c.choice1),
+            self.determined_value(c.property, c.choice2),
+        ) {
+            (Some(a), Some(b)) => c.comparison.holds(a, b),
+            _ => true,
+        }
     }
 
-    pub fn get_property_choice_name(&self, property: &str, i: usize) -> &'a str {
-        self.options[self.get_property(property).unwrap()][i]
+    fn filter_status(&self, filter: &ObjectFilter, object: usize) -> Option<bool> {
+        let mut result = Some(true);
+        for status in filter
+            .0
+            .iter()
+            .map(|(p, set)| self.set_status(*p, set, object))
+        {
+            match status {
+                Some(false) => return Some(false),
+                None => result = None,
+                Some(true) => {}
+            }
+        }
+        result
     }
 
-    pub fn get_property_choice_name_by_nr(&self, p: usize, i: usize) -> &'a str {
-        self.options[p][i]
+    // Whether the choice of the object is in the set.
+    fn set_status(&self, property: usize, set: &[usize], object: usize) -> Option<bool> {
+        let enabled: Vec<usize> = (0..self.choice_count(property))
+            .filter(|ch| self.is_choice_enabled(property, object, *ch))
+            .collect();
+        if enabled.iter().all(|ch| set.contains(ch)) {
+            Some(true)
+        } else if enabled.iter().any(|ch| set.contains(ch)) {
+            None
+        } else {
+            Some(false)
+        }
     }
 
-    pub fn get_property_choice(&self, property: &str, name: &str) -> Option<usize> {
-        let option_pos = self.get_property(property).unwrap();
-        self.options[option_pos].iter().position(|s| *s == name)
+    // Makes the object pass (or fail) the filter.
+    fn apply_filter(
+        &mut self,
+        filter: &ObjectFilter,
+        object: usize,
+        value: bool,
+        change_counter: &mut i32,
+    ) -> bool {
+        let open: Vec<&(usize, Vec<usize>)> = filter
+            .0
+            .iter()
+            .filter(|(p, set)| self.set_status(*p, set, object) != Some(true))
+            .collect();
+        let targets = if value {
+            open
+        } else if open.len() == 1 {
+            // the other conditions hold, so this one must fail
+            open
+        } else {
+            return !open.is_empty();
+        };
+        for (p, set) in targets {
+            let choice_count = self.choice_count(*p);
+            for ch in 0..choice_count {
+                if set.contains(&ch) != value && self.is_choice_enabled(*p, object, ch) {
+                    self.set_choice_enabled(*p, object, ch, false);
+                    *change_counter += 1;
+                }
+            }
+            if !(0..choice_count).any(|ch| self.is_choice_enabled(*p, object, ch)) {
+                return false;
+            }
+        }
+        true
     }
-}
 
-pub struct ZebraBuilder<'a> {
-    zebra: Rc<RefCell<ZebraProperties<'a>>>,
-}
+    fn apply_implication(&mut self, i: &Implication, change_counter: &mut i32) -> bool {
+        match (
+            self.condition_status(&i.condition),
+            self.condition_status(&i.consequence),
+        ) {
+            (Some(true), Some(false)) => false,
+            // modus ponens
+            (Some(true), None) => self.apply_condition(&i.consequence, true, change_counter),
+            // modus tollens
+            (None, Some(false)) => self.apply_condition(&i.condition, false, change_counter),
+            _ => true,
+        }
+    }
 
-impl<'a> ZebraBuilder<'a> {
-    pub fn new() -> ZebraBuilder<'a> {
-        ZebraBuilder {
-            zebra: Rc::new(RefCell::new(ZebraProperties {
-                object_count: 0,
-                properties: vec![],
-                options: vec![],
-                facts: vec![],
-                predicates: vec![],
-            })),
+    fn apply_relation(&mut self, r: &RelationClue, change_counter: &mut i32) -> bool {
+        let object_count = self.props.borrow().get_object_count();
+        for (i, &(p, ch)) in r.choices.iter().enumerate() {
+            for object in 0..object_count {
+                if !self.is_choice_enabled(p, object, ch) {
+                    continue;
+                }
+                let determined = self.is_determined(p, object);
+                let mut supported = false;
+                for place in 0..self.choice_count(r.property) {
+                    if !self.is_choice_enabled(r.property, object, place) {
+                        continue;
+                    }
+                    let mut assigned = vec![];
+                    if self.find_relation_support(r, Some((i, object, place)), &mut assigned) {
+                        supported = true;
+                    } else if determined {
+                        // the object is known to be the one in the relation
+                        if self.is_determined(r.property, object) {
+                            return false;
+                        }
+                        self.set_choice_enabled(r.property, object, place, false);
+                        *change_counter += 1;
+                    }
+                }
+                if !supported {
+                    if determined {
+                        return false;
+                    }
+                    self.set_choice_enabled(p, object, ch, false);
+                    *change_counter += 1;
+                }
+            }
         }
+        true
     }
 
-    pub fn build(&mut self) -> Zebra<'a> {
-        let zebra = self.zebra.borrow();
+    // Searches for objects and their places for the choices of the relation
+    // so that the relation holds. `fixed` pins one choice to an object and a
+    // place, `assigned` holds the (object, place) pairs found so far.
+    fn find_relation_support(
+        &self,
+        r: &RelationClue,
+        fixed: Option<(usize, usize, usize)>,
+        assigned: &mut Vec<(usize, usize)>,
+    ) -> bool {
+        let object_count = self.props.borrow().get_object_count();
+        let place_count = self.choice_count(r.property);
+        let j = assigned.len();
+        if j == r.choices.len() {
+            let places: Vec<usize> = assigned.iter().map(|(_, place)| *place).collect();
+            let arrangement = self.props.borrow().get_arrangement(r.property);
+            return r.relation.holds_in(&places, place_count, arrangement) != r.negated;
+        }
+        let candidates: Vec<(usize, usize)> = match fixed {
+            Some((i, object, place)) if i == j => vec![(object, place)],
+            _ => (0..object_count)
+                .flat_map(|object| (0..place_count).map(move |place| (object, place)))
+                .collect(),
+        };
+        for (object, place) in candidates {
+            if self.is_relation_candidate(r, j, object, place, assigned) {
+                assigned.push((object, place));
+                if self.find_relation_support(r, fixed, assigned) {
+                    return true;
+                }
+                assigned.pop();
+            }
+        }
+        false
+    }
+
+    fn is_relation_candidate(
+        &self,
+        r: &RelationClue,
+        j: usize,
+        object: usize,
+        place: usize,
+        assigned: &[(usize, usize)],
+    ) -> bool {
+        let (p, ch) = r.choices[j];
+        if !self.is_choice_enabled(p, object, ch)
+            || !self.is_choice_enabled(r.property, object, place)
+            || (p == r.property && ch != place)
+        {
+            return false;
+        }
+        let props = self.props.borrow();
+        assigned.iter().enumerate().all(|(l, &(o, pl))| {
+            let (p2, ch2) = r.choices[l];
+            if o == object {
+                pl == place && (p2 != p || ch2 == ch)
+            } else {
+                (pl != place || props.get_occurrence(r.property, place).1 > 1)
+                    && ((p2, ch2) != (p, ch) || props.get_occurrence(p, ch).1 > 1)
+            }
+        })
+    }
+
+    // Cells (row, column) the object may occupy.
+    fn grid_cells(&self, rows: usize, columns: usize, object: usize) -> Vec<(usize, usize)> {
+        let mut result = vec![];
+        for row in 0..self.choice_count(rows) {
+            if !self.is_choice_enabled(rows, object, row) {
+                continue;
+            }
+            for column in 0..self.choice_count(columns) {
+                if self.is_choice_enabled(columns, object, column) {
+                    result.push((row, column));
+                }
+            }
+        }
+        result
+    }
+
+    // Whether another object having the other choice may be in the relation
+    // with the object sitting in the cell. `first` tells whether the object
+    // stands for the first choice of the clue.
+    fn has_grid_support(
+        &self,
+        g: &GridClue,
+        first: bool,
+        object: usize,
+        cell: (usize, usize),
+    ) -> bool {
+        let object_count = self.props.borrow().get_object_count();
+        let (p, ch) = if first { g.choice2 } else { g.choice1 };
+        (0..object_count)
+            .filter(|o| *o != object && self.is_choice_enabled(p, *o, ch))
+            .any(|o| {
+                self.grid_cells(g.rows, g.columns, o)
+                    .into_iter()
+                    .any(|other| {
+                        other != cell
+                            && if first {
+                                g.relation.holds(cell, other)
+                            } else {
+                                g.relation.holds(other, cell)
+                            }
+                    })
+            })
+    }
+
+    fn apply_grid_relation(&mut self, g: &GridClue, change_counter: &mut i32) -> bool {
+        let object_count = self.props.borrow().get_object_count();
+        for (first, (p, ch)) in [(true, g.choice1), (false, g.choice2)] {
+            for object in 0..object_count {
+                if !self.is_choice_enabled(p, object, ch) {
+                    continue;
+                }
+                let supported: Vec<(usize, usize)> = self
+                    .grid_cells(g.rows, g.columns, object)
+                    .into_iter()
+                    .filter(|cell| self.has_grid_support(g, first, object, *cell))
+                    .collect();
+                if supported.is_empty() {
+                    if self.is_determined(p, object) {
+                        return false;
+                    }
+                    self.set_choice_enabled(p, object, ch, false);
+                    *change_counter += 1;
+                } else if self.is_determined(p, object) {
+                    // keep the rows and columns of the supported cells only
+                    for row in 0..self.choice_count(g.rows) {
+                        if self.is_choice_enabled(g.rows, object, row)
+                            && !supported.iter().any(|(r, _)| *r == row)
+                        {
+                            self.set_choice_enabled(g.rows, object, row, false);
+                            *change_counter += 1;
+                        }
+                    }
+                    for column in 0..self.choice_count(g.columns) {
+                        if self.is_choice_enabled(g.columns, object, column)
+                            && !supported.iter().any(|(_, c)| *c == column)
+                        {
+                            self.set_choice_enabled(g.columns, object, column, false);
+                            *change_counter += 1;
+                        }
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    // No two objects share a cell of the grid.
+    fn apply_grid_cells(&mut self, rows: usize, columns: usize, change_counter: &mut i32) -> bool {
+        let object_count = self.props.borrow().get_object_count();
+        for object in 0..object_count {
+            let (row, column) = match (
+                self.get_determined(rows, object),
+                self.get_determined(columns, object),
+            ) {
+                (Some(row), Some(column)) => (row, column),
+                _ => continue,
+            };
+            for other in (0..object_count).filter(|o| *o != object) {
+                let (p, ch) = match (
+                    self.get_determined(rows, other),
+                    self.get_determined(columns, other),
+                ) {
+                    (Some(r), Some(c)) if (r, c) == (row, column) => return false,
+                    (Some(r), _) if r == row => (columns, column),
+                    (_, Some(c)) if c == column => (rows, row),
+                    _ => continue,
+                };
+                if self.is_choice_enabled(p, other, ch) {
+                    self.set_choice_enabled(p, other, ch, false);
+                    *change_counter += 1;
+                }
+            }
+        }
+        true
+    }
+
+    /// Returns the choice of a determined property, `None` while there are
+    /// several candidates left.
+    pub fn get_determined(&self, property: usize, object: usize) -> Option<usize> {
+        if self.is_determined(property, object) {
+            Some(self.find_determined(property, object))
+        } else {
+            None
+        }
+    }
+
+    pub fn get_clue_count(&self) -> usize {
+        self.props.borrow().clues.len()
+    }
+
+    pub fn get_clue_text(&self, clue: usize) -> String {
+        self.props.borrow().clues[clue].text.clone()
+    }
+
+    pub fn get_clue_kind(&self, clue: usize) -> ClueKind {
+        self.props.borrow().clues[clue].kind
+    }
+
+    /// Penalty of a soft clue, `None` when the clue is hard.
+    pub fn get_clue_weight(&self, clue: usize) -> Option<i64> {
+        self.props.borrow().clues[clue].weight
+    }
+
+    /// Whether the clue surely does not hold in this state.
+    pub fn is_clue_violated(&self, clue: usize) -> bool {
+        let mut z = self.clone();
+        let mut change_counter = 0;
+        !z.apply_clue(clue, &mut change_counter)
+    }
+
+    pub fn is_clue_enabled(&self, clue: usize) -> bool {
+        self.enabled_clues[clue]
+    }
+
+    /// Disabled clues are ignored by the propagation. The setting belongs to
+    /// this state and is inherited by its clones made afterwards.
+    pub fn set_clue_enabled(&mut self, clue: usize, enabled: bool) {
+        self.enabled_clues.set(clue, enabled);
+    }
+
+    // Soft clues are only evaluated, never propagated.
+    pub(crate) fn is_clue_enforced(&self, clue: usize) -> bool {
+        self.is_clue_enabled(clue) && self.get_clue_weight(clue).is_none()
+    }
+
+    // A choice whose declaration is disabled may be shared by several
+    // objects (or by none).
+    pub(crate) fn is_choice_exempt(&self, property: usize, choice: usize) -> bool {
+        match self.props.borrow().declarations[property][choice] {
+            Some(clue) => !self.is_clue_enforced(clue),
+            None => false,
+        }
+    }
+}
+
+impl<'a> Zebra<'a> {
+    pub fn get_properties(&self) -> Ref<'_, ZebraProperties<'a>> {
+        self.props.borrow()
+    }
+
+    /// Returns the choice name of a determined property of the named
+    /// object, `None` while undetermined.
+    pub fn get_determined_name(&self, object: &str, property: &str) -> Option<&'a str> {
+        let props = self.props.borrow();
+        let object = props.get_object(object)?;
+        let property = props.get_property(property)?;
+        self.get_determined(property, object)
+            .map(|ch| props.get_property_choice_name_by_nr(property, ch))
+    }
+
+    /// Gives the named object the named choice, returns false when one of
+    /// the names is unknown.
+    pub fn set_determined_name(&mut self, object: &str, property: &str, choice: &str) -> bool {
+        let props = self.props.clone();
+        let props = props.borrow();
+        let (object, property) = match (props.get_object(object), props.get_property(property)) {
+            (Some(object), Some(property)) => (object, property),
+            _ => return false,
+        };
+        match props.options[property].iter().position(|ch| *ch == choice) {
+            Some(choice) => {
+                self.determine_choice(property, object, choice);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the name of the only object which may have the choice, once
+    /// determined.
+    pub fn get_owner_name(&self, property: &str, choice: &str) -> Option<&'a str> {
+        let props = self.props.borrow();
+        let p = props.get_property(property)?;
+        let ch = props.get_property_choice(property, choice)?;
+        let mut owners =
+            (0..props.get_object_count()).filter(|o| self.is_choice_enabled(p, *o, ch));
+        match (owners.next(), owners.next()) {
+            (Some(o), None) if self.is_determined(p, o) => Some(props.get_object_name(o)),
+            _ => None,
+        }
+    }
+}
+
+// Checks that between `min` and `max` items can hold. Returns `None` when this
+// is impossible, otherwise the value all undecided items are forced to, if any.
+fn count_statuses(statuses: &[Option<bool>], min: usize, max: usize) -> Option<Option<bool>> {
+    let holding = statuses.iter().filter(|s| **s == Some(true)).count();
+    let open = statuses.iter().filter(|s| s.is_none()).count();
+    if holding > max || holding + open < min {
+        None
+    } else if open > 0 && holding == max {
+        Some(Some(false))
+    } else if open > 0 && holding + open == min {
+        Some(Some(true))
+    } else {
+        Some(None)
+    }
+}
+
+impl Display for Zebra<'_> {
+    fn fmt(&self, dest: &mut fmt::Formatter) -> fmt::Result {
+        for property in 0..self.props.borrow().get_property_count() {
+            writeln!(
+                dest,
+                "{} -----",
+                self.props.borrow().get_property_name(property)
+            )?;
+            for object in 0..self.props.borrow().get_object_count() {
+                write!(dest, "{}:", self.props.borrow().get_object_name(object))?;
+                for choice in 0..self.choice_count(property) {
+                    if self.is_choice_enabled(property, object, choice) {
+                        write!(
+                            dest,
+                            "{} ",
+                            self.props
+                                .borrow()
+                                .get_property_choice_name_by_nr(property, choice)
+                        )?;
+                    }
+                }
+                writeln!(dest)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl State for Zebra<'_> {
+    fn is_solution(&self) -> bool {
+        for object in 0..self.props.borrow().get_object_count() {
+            for property in 0..self.props.borrow().get_property_count() {
+                if !self.is_determined(property, object) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn branch(&self) -> Vec<Self> {
+        self.decisions()
+            .iter()
+            .map(|d| {
+                let mut z = self.clone();
+                z.decide(d);
+                z
+            })
+            .collect()
+    }
+
+    fn propagate(&mut self) -> Result<Changed, Conflict> {
+        let mut change_counter = 0;
+        if !self.apply_facts(&mut change_counter)
+            || !self.apply_predicates(&mut change_counter)
+            || !self.apply_permutations(&mut change_counter)
+        {
+            return Err(Conflict);
+        }
+        Ok(Changed::from_count(change_counter))
+    }
+}
+
+impl Decide for Zebra<'_> {
+    /// Property, object and choice determined for it.
+    type Decision = (usize, usize, usize);
+
+    fn decisions(&self) -> Vec<Self::Decision> {
+        for property in 0..self.props.borrow().get_property_count() {
+            for object in 0..self.props.borrow().get_object_count() {
+                if !self.is_determined(property, object) {
+                    return (0..self.choice_count(property))
+                        .filter(|ch| self.is_choice_enabled(property, object, *ch))
+                        .map(|ch| (property, object, ch))
+                        .collect();
+                }
+            }
+        }
+        vec![]
+    }
+
+    fn domain(&self, &(property, object, _): &Self::Decision) -> Vec<Self::Decision> {
+        (0..self.choice_count(property))
+            .map(|ch| (property, object, ch))
+            .collect()
+    }
+
+    fn decide(&mut self, &(property, object, choice): &Self::Decision) {
+        self.determine_choice(property, object, choice);
+    }
+
+    fn entails(&self, &(property, object, choice): &Self::Decision) -> bool {
+        self.get_determined(property, object) == Some(choice)
+    }
+}
+
+// The propagators run in turn by `State::propagate`.
+impl Zebra<'_> {
+    fn apply_facts(&mut self, change_counter: &mut i32) -> bool {
+        let props2 = self.props.clone();
+        for f in &props2.borrow().facts {
+            if self.is_clue_enforced(f.clue) && !self.apply_fact(f, change_counter) {
+                return false;
+            }
+        }
+        for f in &props2.borrow().negative_facts {
+            if self.is_clue_enforced(f.clue) && !self.apply_negative_fact(f, change_counter) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn apply_predicates(&mut self, change_counter: &mut i32) -> bool {
+        let props2 = self.props.clone();
+        for pred in &props2.borrow().predicates {
+            if self.is_clue_enforced(pred.clue) && !self.apply_predicate(pred) {
+                return false;
+            }
+        }
+        for r in &props2.borrow().relations {
+            if self.is_clue_enforced(r.clue) && !self.apply_relation(r, change_counter) {
+                return false;
+            }
+        }
+        for d in &props2.borrow().disjunctions {
+            if self.is_clue_enforced(d.clue) && !self.apply_disjunction(d, change_counter) {
+                return false;
+            }
+        }
+        for i in &props2.borrow().implications {
+            if self.is_clue_enforced(i.clue) && !self.apply_implication(i, change_counter) {
+                return false;
+            }
+        }
+        for c in &props2.borrow().cardinalities {
+            if self.is_clue_enforced(c.clue) && !self.apply_cardinality(c, change_counter) {
+                return false;
+            }
+        }
+        for c in &props2.borrow().comparisons {
+            if self.is_clue_enforced(c.clue) && !self.apply_comparison(c, change_counter) {
+                return false;
+            }
+        }
+        for c in &props2.borrow().arithmetic {
+            if self.is_clue_enforced(c.clue) && !apply_arithmetic(self, c, change_counter) {
+                return false;
+            }
+        }
+        for g in &props2.borrow().grid_relations {
+            if self.is_clue_enforced(g.clue) && !self.apply_grid_relation(g, change_counter) {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub(crate) fn apply_permutations(&mut self, change_counter: &mut i32) -> bool {
+        let props2 = self.props.clone();
+        let object_count = props2.borrow().get_object_count();
+        for property in 0..props2.borrow().get_property_count() {
+            for choice in 0..self.choice_count(property) {
+                if self.is_choice_exempt(property, choice) {
+                    continue;
+                }
+                let candidates: Vec<usize> = (0..object_count)
+                    .filter(|o| self.is_choice_enabled(property, *o, choice))
+                    .collect();
+                let owners: Vec<usize> = candidates
+                    .iter()
+                    .copied()
+                    .filter(|o| self.is_determined(property, *o))
+                    .collect();
+                let (min, max) = props2.borrow().get_occurrence(property, choice);
+                if owners.len() > max || candidates.len() < min {
+                    return false;
+                }
+                if owners.len() == max {
+                    // once all the owners of a choice are known, nobody else has it
+                    for object2 in candidates.into_iter().filter(|o| !owners.contains(o)) {
+                        *change_counter += 1;
+                        self.set_choice_enabled(property, object2, choice, false);
+                    }
+                } else if candidates.len() == min {
+                    // the candidates are the only objects left to have it
+                    for object2 in candidates.into_iter().filter(|o| !owners.contains(o)) {
+                        *change_counter += 1;
+                        self.determine_choice(property, object2, choice);
+                    }
+                }
+            }
+        }
+        for &(rows, columns) in &props2.borrow().grids {
+            if !self.apply_grid_cells(rows, columns, change_counter) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl<'a> ZebraProperties<'a> {
+    pub fn get_object_count(&self) -> usize {
+        self.object_count
+    }
+
+    pub fn get_property_count(&self) -> usize {
+        self.properties.len()
+    }
+
+    pub fn get_property_name(&self, i: usize) -> &'a str {
+        self.properties[i]
+    }
+
+    pub fn get_property(&self, name: &str) -> Option<usize> {
+        self.properties.iter().position(|s| *s == name)
+    }
+
+    pub fn get_property_choice_name(&self, property: &str, i: usize) -> &'a str {
+        self.options[self.get_property(property).unwrap()][i]
+    }
+
+    pub fn get_property_choice_name_by_nr(&self, p: usize, i: usize) -> &'a str {
+        self.options[p][i]
+    }
+
+    pub fn get_property_choice(&self, property: &str, name: &str) -> Option<usize> {
+        let option_pos = self.get_property(property).unwrap();
+        self.options[option_pos].iter().position(|s| *s == name)
+    }
+
+    /// Value of a choice. Choices of numeric properties have the number in
+    /// their name, the other ones are numbered in the order of declaration.
+    pub fn get_value(&self, p: usize, i: usize) -> i64 {
+        self.numbers[p][i]
+    }
+
+    /// Property whose choices name the objects, the first one unless
+    /// declared by `ZebraBuilder::objects`.
+    pub fn get_identity(&self) -> usize {
+        self.identity
+    }
+
+    pub fn get_object_name(&self, object: usize) -> &'a str {
+        self.options[self.identity][object]
+    }
+
+    pub fn get_object(&self, name: &str) -> Option<usize> {
+        self.options[self.identity].iter().position(|s| *s == name)
+    }
+
+    pub fn get_choice_count(&self, p: usize) -> usize {
+        self.options[p].len()
+    }
+
+    /// The choice clue which declared the choice, `None` when the choice was
+    /// first named by another clue.
+    pub fn get_declaration(&self, p: usize, i: usize) -> Option<usize> {
+        self.declarations[p][i]
+    }
+
+    /// Lowest and highest number of objects having the choice in a solution.
+    pub fn get_occurrence(&self, p: usize, i: usize) -> (usize, usize) {
+        self.occurrences[p][i]
+    }
+
+    pub fn get_arrangement(&self, p: usize) -> Arrangement {
+        self.arrangements[p]
+    }
+
+    pub fn is_numeric(&self, p: usize) -> bool {
+        self.numeric[p]
+    }
+
+    // Panics on a relation which means nothing in the arrangement of its
+    // property, such as `Opposite` on a line.
+    fn check_relations(&self) {
+        let conditions = self
+            .disjunctions
+            .iter()
+            .flat_map(|d| d.conditions.iter())
+            .chain(
+                self.implications
+                    .iter()
+                    .flat_map(|i| [&i.condition, &i.consequence]),
+            )
+            .chain(self.cardinalities.iter().flat_map(|c| match &c.counted {
+                Counted::Conditions(conditions) => conditions.iter(),
+                Counted::Objects(_) => [].iter(),
+            }));
+        let relations = self
+            .relations
+            .iter()
+            .chain(conditions.filter_map(|c| match c {
+                Condition::Relation(r) => Some(r),
+                _ => None,
+            }));
+        for r in relations {
+            let arrangement = self.get_arrangement(r.property);
+            if !r.relation.is_defined_in(arrangement) {
+                panic!(
+                    "Relation {:?} is not defined on {:?} property {}",
+                    r.relation,
+                    arrangement,
+                    self.get_property_name(r.property)
+                );
+            }
+        }
+    }
+}
+
+pub struct ZebraBuilder<'a> {
+    zebra: Rc<RefCell<ZebraProperties<'a>>>,
+}
+
+impl<'a> ZebraBuilder<'a> {
+    pub fn new() -> ZebraBuilder<'a> {
+        ZebraBuilder {
+            zebra: Rc::new(RefCell::new(ZebraProperties {
+                object_count: 0,
+                properties: vec![],
+                options: vec![],
+                declarations: vec![],
+                occurrences: vec![],
+                offsets: vec![],
+                identity: 0,
+                optional: vec![],
+                grids: vec![],
+                numeric: vec![],
+                arrangements: vec![],
+                numbers: vec![],
+                facts: vec![],
+                negative_facts: vec![],
+                predicates: vec![],
+                relations: vec![],
+                disjunctions: vec![],
+                implications: vec![],
+                cardinalities: vec![],
+                comparisons: vec![],
+                arithmetic: vec![],
+                grid_relations: vec![],
+                clues: vec![],
+            })),
+        }
+    }
+
+    pub fn build(&mut self) -> Zebra<'a> {
+        let numbers = self.parse_numbers();
+        self.zebra.borrow_mut().numbers = numbers;
+        let mut zebra = self.zebra.borrow_mut();
+        for p in zebra.optional.clone() {
+            let none = zebra.options[p].iter().position(|ch| *ch == NONE).unwrap();
+            zebra.occurrences[p][none] = (0, zebra.object_count);
+        }
         for i in 0..zebra.options.len() {
-            let chlen = zebra.options[i].len();
-            if chlen != zebra.object_count {
+            let min: usize = zebra.occurrences[i].iter().map(|(min, _)| min).sum();
+            let max: usize = zebra.occurrences[i].iter().map(|(_, max)| max).sum();
+            if min > zebra.object_count || max < zebra.object_count {
                 panic!(
-                    "Invalid number choices in property {} is {} should be {}",
+                    "Invalid number choices in property {} is {} to {} should be {}",
                     zebra.get_property_name(i),
-                    chlen,
+                    min,
+                    max,
                     zebra.object_count
                 );
             }
         }
-        let bit_count =
-            zebra.get_property_count() * zebra.get_object_count() * zebra.get_object_count();
-        let mut result = Zebra {
-            props: Rc::clone(&self.zebra),
-            values: bitvec![1; bit_count],
-        };
-        result.fix_first_property();
-        result
+        let identity = zebra.identity;
+        if zebra.get_property_count() > 0
+            && (zebra.options[identity].len() != zebra.object_count
+                || zebra.occurrences[identity].iter().any(|o| *o != (1, 1)))
+        {
+            panic!(
+                "Property {} identifies the objects, its choices cannot be shared",
+                zebra.get_property_name(identity)
+            );
+        }
+        let mut bit_count = 0;
+        zebra.offsets = vec![];
+        for p in 0..zebra.get_property_count() {
+            zebra.offsets.push(bit_count);
+            bit_count += zebra.get_object_count() * zebra.get_choice_count(p);
+        }
+        zebra.check_relations();
+        let clue_count = zebra.clues.len();
+        drop(zebra);
+        let mut result = Zebra {
+            props: Rc::clone(&self.zebra),
+            values: bitvec![1; bit_count],
+            enabled_clues: bitvec![1; clue_count],
+        };
+        result.break_symmetry();
+        result
+    }
+
+    fn parse_numbers(&self) -> Vec<Vec<i64>> {
+        let zebra = self.zebra.borrow();
+        let mut result = vec![];
+        for (p, options) in zebra.options.iter().enumerate() {
+            if !zebra.numeric[p] {
+                result.push((0..options.len() as i64).collect());
+                continue;
+            }
+            let parse = |ch: &&str| match ch.parse::<i64>() {
+                Ok(value) => value,
+                Err(_) => panic!(
+                    "Choice {} of numeric property {} is not a number",
+                    ch,
+                    zebra.get_property_name(p)
+                ),
+            };
+            result.push(options.iter().map(parse).collect());
+        }
+        result
+    }
+
+    pub fn set_object_count(&mut self, size: usize) -> &mut Self {
+        self.zebra.borrow_mut().object_count = size;
+        self
+    }
+
+    /// The object having `choice1` of `property1` has `choice2` of
+    /// `property2`. The order of the choices does not matter. When one of
+    /// them may be shared by more objects, such as a color of several
+    /// houses, the fact only says that the objects having it include the one
+    /// having the other choice, "the Englishman lives in a red house".
+    pub fn fact(
+        &mut self,
+        property1: &'a str,
+        choice1: &'a str,
+        property2: &'a str,
+        choice2: &'a str,
+    ) -> &mut Self {
+        let text = Literal::fact(property1, choice1, property2, choice2).to_string();
+        let clue = self.add_clue(ClueKind::Fact, text);
+        let choice1 = self.ensure_choice(property1, choice1);
+        let choice2 = self.ensure_choice(property2, choice2);
+        self.zebra.borrow_mut().facts.push(Fact {
+            choice1,
+            choice2,
+            clue,
+        });
+        self
+    }
+
+    /// The object having `choice1` of `property1` does not have `choice2` of
+    /// `property2`.
+    pub fn not_fact(
+        &mut self,
+        property1: &'a str,
+        choice1: &'a str,
+        property2: &'a str,
+        choice2: &'a str,
+    ) -> &mut Self {
+        let text = Literal::not_fact(property1, choice1, property2, choice2).to_string();
+        let clue = self.add_clue(ClueKind::NegativeFact, text);
+        let choice1 = self.ensure_choice(property1, choice1);
+        let choice2 = self.ensure_choice(property2, choice2);
+        self.zebra.borrow_mut().negative_facts.push(Fact {
+            choice1,
+            choice2,
+            clue,
+        });
+        self
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn predicate(
+        &mut self,
+        property1: &'a str,
+        choice1: &'a str,
+        property2: &'a str,
+        choice2: &'a str,
+        property3: &'a str,
+        property4: &'a str,
+        test: PredicateTest,
+    ) -> &mut Self {
+        let text = format!(
+            "predicate: {}={}, {}={} on {}/{}",
+            property1, choice1, property2, choice2, property3, property4
+        );
+        let clue = self.add_clue(ClueKind::Predicate, text);
+        let choice1 = self.ensure_choice(property1, choice1);
+        let choice2 = self.ensure_choice(property2, choice2);
+        let property3 = self.ensure_property(property3);
+        let property4 = self.ensure_property(property4);
+        self.zebra.borrow_mut().predicates.push(Predicate {
+            choice1,
+            choice2,
+            property3,
+            property4,
+            test,
+            clue,
+        });
+        self
+    }
+
+    /// Objects having `choices` (pairs of property and choice name) are in
+    /// `relation` by their places in `property`. The places follow the order
+    /// in which the choices of `property` are declared.
+    pub fn relation(
+        &mut self,
+        relation: Relation,
+        property: &'a str,
+        choices: Vec<(&'a str, &'a str)>,
+    ) -> &mut Self {
+        let text = Literal::relation(relation, property, choices.clone()).to_string();
+        let clue = self.add_clue(ClueKind::Relation, text);
+        let r = self.resolve_relation(relation, property, choices, clue);
+        self.zebra.borrow_mut().relations.push(r);
+        self
+    }
+
+    /// At least one of the literals holds.
+    pub fn any_of(&mut self, literals: Vec<Literal<'a>>) -> &mut Self {
+        self.disjunction(literals, false)
+    }
+
+    /// Exactly one of the literals holds.
+    pub fn either(&mut self, literals: Vec<Literal<'a>>) -> &mut Self {
+        self.disjunction(literals, true)
+    }
+
+    fn disjunction(&mut self, literals: Vec<Literal<'a>>, exclusive: bool) -> &mut Self {
+        let names: Vec<String> = literals.iter().map(|l| l.to_string()).collect();
+        let text = format!(
+            "{}: {}",
+            if exclusive { "either" } else { "any of" },
+            names.join(" | ")
+        );
+        let clue = self.add_clue(ClueKind::Disjunction, text);
+        let conditions = literals
+            .into_iter()
+            .map(|l| self.resolve(l, clue))
+            .collect();
+        self.zebra.borrow_mut().disjunctions.push(Disjunction {
+            conditions,
+            exclusive,
+            clue,
+        });
+        self
+    }
+
+    /// Between `min` and `max` (inclusive) of the literals hold.
+    pub fn cardinality(&mut self, literals: Vec<Literal<'a>>, min: usize, max: usize) -> &mut Self {
+        let names: Vec<String> = literals.iter().map(|l| l.to_string()).collect();
+        let text = format!("{}..{} of: {}", min, max, names.join(" | "));
+        let clue = self.add_clue(ClueKind::Cardinality, text);
+        let conditions = literals
+            .into_iter()
+            .map(|l| self.resolve(l, clue))
+            .collect();
+        self.zebra.borrow_mut().cardinalities.push(Cardinality {
+            counted: Counted::Conditions(conditions),
+            min,
+            max,
+            clue,
+        });
+        self
+    }
+
+    pub fn exactly(&mut self, count: usize, literals: Vec<Literal<'a>>) -> &mut Self {
+        self.cardinality(literals, count, count)
+    }
+
+    pub fn at_least(&mut self, count: usize, literals: Vec<Literal<'a>>) -> &mut Self {
+        let max = literals.len();
+        self.cardinality(literals, count, max)
+    }
+
+    pub fn at_most(&mut self, count: usize, literals: Vec<Literal<'a>>) -> &mut Self {
+        self.cardinality(literals, 0, count)
+    }
+
+    /// Between `min` and `max` (inclusive) objects pass the filter. An object
+    /// passes when, for each listed property, it has one of the listed
+    /// choices.
+    pub fn object_cardinality(
+        &mut self,
+        filter: Vec<(&'a str, Vec<&'a str>)>,
+        min: usize,
+        max: usize,
+    ) -> &mut Self {
+        let names: Vec<String> = filter
+            .iter()
+            .map(|(p, set)| format!("{} in {{{}}}", p, set.join(", ")))
+            .collect();
+        let text = format!("{}..{} objects with {}", min, max, names.join(" and "));
+        let clue = self.add_clue(ClueKind::Cardinality, text);
+        let filter = filter
+            .into_iter()
+            .map(|(p, set)| {
+                let choices = set.iter().map(|ch| self.ensure_choice(p, ch).1).collect();
+                (self.ensure_property(p), choices)
+            })
+            .collect();
+        self.zebra.borrow_mut().cardinalities.push(Cardinality {
+            counted: Counted::Objects(ObjectFilter(filter)),
+            min,
+            max,
+            clue,
+        });
+        self
+    }
+
+    /// Declares a property with numeric choices, such as ages or prices.
+    /// The choice names must parse as integers.
+    pub fn numeric(&mut self, property: &'a str, choices: Vec<&'a str>) -> &mut Self {
+        self.choice(property, choices);
+        let p = self.ensure_property(property);
+        self.zebra.borrow_mut().numeric[p] = true;
+        self
+    }
+
+    /// Lays out the places of `property`, see `Arrangement`. At most one
+    /// property may be circular. Its seats are unlabeled: solutions differing
+    /// only by a rotation are the same one, so clues must not name a seat.
+    pub fn arrangement(&mut self, property: &'a str, arrangement: Arrangement) -> &mut Self {
+        let p = self.ensure_property(property);
+        let circle = self
+            .zebra
+            .borrow()
+            .arrangements
+            .iter()
+            .position(|a| *a == Arrangement::Circle);
+        if arrangement == Arrangement::Circle && matches!(circle, Some(other) if other != p) {
+            panic!(
+                "Property {} cannot be circular, only one property may be",
+                property
+            );
+        }
+        self.zebra.borrow_mut().arrangements[p] = arrangement;
+        self
+    }
+
+    /// Between `min` and `max` objects have each of `choices`, such as a
+    /// color shared by two houses or a pet nobody owns. Choices occur exactly
+    /// once unless declared otherwise.
+    pub fn occurrence(
+        &mut self,
+        property: &'a str,
+        choices: Vec<&'a str>,
+        min: usize,
+        max: usize,
+    ) -> &mut Self {
+        if min > max {
+            panic!(
+                "Invalid occurrence {} to {} in property {}",
+                min, max, property
+            );
+        }
+        for ch in choices {
+            let (p, ch) = self.ensure_choice(property, ch);
+            self.zebra.borrow_mut().occurrences[p][ch] = (min, max);
+        }
+        self
+    }
+
+    /// Names the objects by the choices of `property`, "name" with "Alice"
+    /// and "Bob" for example. The object count follows the number of names.
+    pub fn objects(&mut self, property: &'a str, names: Vec<&'a str>) -> &mut Self {
+        let count = names.len();
+        for name in names {
+            self.ensure_choice(property, name);
+        }
+        let p = self.ensure_property(property);
+        {
+            let mut zebra = self.zebra.borrow_mut();
+            zebra.identity = p;
+            zebra.object_count = count;
+        }
+        self
     }
 
-    pub fn set_object_count(&mut self, size: usize) -> &mut Self {
-        self.zebra.borrow_mut().object_count = size;
+    /// Makes `property` optional: any number of objects may have the `NONE`
+    /// choice instead of a real one, like a resident without a pet. Clues
+    /// may name it as any other choice, e.g. `("pet", NONE)`.
+    pub fn optional(&mut self, property: &'a str) -> &mut Self {
+        let (p, _) = self.ensure_choice(property, NONE);
+        self.zebra.borrow_mut().optional.push(p);
         self
     }
 
-    pub fn fact(
+    /// Places the objects on a grid, one object in each cell. Each row is
+    /// shared by as many objects as there are columns and vice versa.
+    pub fn grid(
         &mut self,
-        property1: &'a str,
-        choice1: &'a str,
-        property2: &'a str,
-        choice2: &'a str,
+        rows_property: &'a str,
+        rows: Vec<&'a str>,
+        columns_property: &'a str,
+        columns: Vec<&'a str>,
     ) -> &mut Self {
-        let choice1 = self.ensure_choice(property1, choice1);
-        let choice2 = self.ensure_choice(property2, choice2);
-        self.zebra
-            .borrow_mut()
-            .facts
-            .push(Fact { choice1, choice2 });
+        let (row_count, column_count) = (rows.len(), columns.len());
+        self.choice(rows_property, rows);
+        self.choice(columns_property, columns);
+        let rows_property = self.ensure_property(rows_property);
+        let columns_property = self.ensure_property(columns_property);
+        {
+            let mut zebra = self.zebra.borrow_mut();
+            zebra.occurrences[rows_property] = vec![(column_count, column_count); row_count];
+            zebra.occurrences[columns_property] = vec![(row_count, row_count); column_count];
+            zebra.grids.push((rows_property, columns_property));
+        }
         self
     }
 
-    pub fn predicate(
+    /// Objects having `choice1` and `choice2` are in `relation` by their
+    /// cells on the grid of `rows` and `columns`.
+    pub fn grid_relation(
         &mut self,
-        property1: &'a str,
-        choice1: &'a str,
-        property2: &'a str,
-        choice2: &'a str,
-        property3: &'a str,
-        property4: &'a str,
-        test: Box<dyn Fn(&str, &str) -> bool>,
+        relation: GridRelation,
+        rows: &'a str,
+        columns: &'a str,
+        choice1: (&'a str, &'a str),
+        choice2: (&'a str, &'a str),
     ) -> &mut Self {
-        let choice1 = self.ensure_choice(property1, choice1);
-        let choice2 = self.ensure_choice(property2, choice2);
-        let property3 = self.ensure_property(property3);
-        let property4 = self.ensure_property(property4);
-        self.zebra.borrow_mut().predicates.push(Predicate {
+        let text = format!(
+            "grid: {}={} {:?} {}={} on {}, {}",
+            choice1.0, choice1.1, relation, choice2.0, choice2.1, rows, columns
+        );
+        let clue = self.add_clue(ClueKind::GridRelation, text);
+        let choice1 = self.ensure_choice(choice1.0, choice1.1);
+        let choice2 = self.ensure_choice(choice2.0, choice2.1);
+        let rows = self.ensure_property(rows);
+        let columns = self.ensure_property(columns);
+        self.zebra.borrow_mut().grid_relations.push(GridClue {
+            relation,
+            rows,
+            columns,
             choice1,
             choice2,
-            property3,
-            property4,
-            test,
+            clue,
+        });
+        self
+    }
+
+    /// The values of `property` of the objects having `choice1` and `choice2`
+    /// are in `comparison`, for example `Comparison::Greater` for "the tea
+    /// drinker is older than the dog owner".
+    pub fn compare(
+        &mut self,
+        property: &'a str,
+        comparison: Comparison,
+        choice1: (&'a str, &'a str),
+        choice2: (&'a str, &'a str),
+    ) -> &mut Self {
+        let text = format!(
+            "compare: {}={} {:?} {}={} on {}",
+            choice1.0, choice1.1, comparison, choice2.0, choice2.1, property
+        );
+        let clue = self.add_clue(ClueKind::Comparison, text);
+        let choice1 = self.ensure_choice(choice1.0, choice1.1);
+        let choice2 = self.ensure_choice(choice2.0, choice2.1);
+        let property = self.ensure_property(property);
+        self.zebra.borrow_mut().comparisons.push(ComparisonClue {
+            comparison,
+            property,
+            choice1,
+            choice2,
+            clue,
+        });
+        self
+    }
+
+    /// The values of both expressions are in `comparison`, for example "the
+    /// sum of the ages of the neighbours of the red house is 60". Values of
+    /// numeric properties are used as declared, other properties are
+    /// numbered from 0 in the order of declaration.
+    pub fn arithmetic(
+        &mut self,
+        lhs: Expr<'a>,
+        comparison: Comparison,
+        rhs: Expr<'a>,
+    ) -> &mut Self {
+        let text = format!("arithmetic: {} {:?} {}", lhs, comparison, rhs);
+        let clue = self.add_clue(ClueKind::Arithmetic, text);
+        let lhs = self.resolve_expr(lhs);
+        let rhs = self.resolve_expr(rhs);
+        self.zebra.borrow_mut().arithmetic.push(ArithmeticClue {
+            lhs,
+            comparison,
+            rhs,
+            clue,
+        });
+        self
+    }
+
+    fn resolve_expr(&mut self, expr: Expr<'a>) -> Term {
+        match expr {
+            Expr::Const(c) => Term::Const(c),
+            Expr::Value(property, selector) => {
+                let property = self.ensure_property(property);
+                Term::Value(property, self.resolve_selector(selector))
+            }
+            Expr::Add(a, b) => Term::Add(
+                Box::new(self.resolve_expr(*a)),
+                Box::new(self.resolve_expr(*b)),
+            ),
+            Expr::Sub(a, b) => Term::Sub(
+                Box::new(self.resolve_expr(*a)),
+                Box::new(self.resolve_expr(*b)),
+            ),
+            Expr::Mul(a, b) => Term::Mul(
+                Box::new(self.resolve_expr(*a)),
+                Box::new(self.resolve_expr(*b)),
+            ),
+        }
+    }
+
+    fn resolve_selector(&mut self, selector: Selector<'a>) -> Target {
+        match selector {
+            Selector::Has(property, choice) => Target::Has(self.ensure_choice(property, choice)),
+            Selector::Offset(inner, property, offset) => {
+                let inner = self.resolve_selector(*inner);
+                Target::Offset(Box::new(inner), self.ensure_property(property), offset)
+            }
+        }
+    }
+
+    /// If `condition` holds then `consequence` holds too.
+    pub fn implies(&mut self, condition: Literal<'a>, consequence: Literal<'a>) -> &mut Self {
+        let text = format!("if {} then {}", condition, consequence);
+        let clue = self.add_clue(ClueKind::Implication, text);
+        let condition = self.resolve(condition, clue);
+        let consequence = self.resolve(consequence, clue);
+        self.zebra.borrow_mut().implications.push(Implication {
+            condition,
+            consequence,
+            clue,
         });
         self
     }
 
+    fn resolve(&mut self, literal: Literal<'a>, clue: usize) -> Condition {
+        match literal {
+            Literal::Fact((p1, ch1), (p2, ch2)) => Condition::Same(Fact {
+                choice1: self.ensure_choice(p1, ch1),
+                choice2: self.ensure_choice(p2, ch2),
+                clue,
+            }),
+            Literal::NotFact((p1, ch1), (p2, ch2)) => Condition::Different(Fact {
+                choice1: self.ensure_choice(p1, ch1),
+                choice2: self.ensure_choice(p2, ch2),
+                clue,
+            }),
+            Literal::Relation(relation, property, choices) => {
+                Condition::Relation(self.resolve_relation(relation, property, choices, clue))
+            }
+        }
+    }
+
+    fn resolve_relation(
+        &mut self,
+        relation: Relation,
+        property: &'a str,
+        choices: Vec<(&'a str, &'a str)>,
+        clue: usize,
+    ) -> RelationClue {
+        if choices.len() != relation.arity() {
+            panic!(
+                "Relation {:?} needs {} choices, got {}",
+                relation,
+                relation.arity(),
+                choices.len()
+            );
+        }
+        let choices = choices
+            .into_iter()
+            .map(|(p, ch)| self.ensure_choice(p, ch))
+            .collect();
+        let property = self.ensure_property(property);
+        RelationClue {
+            relation,
+            property,
+            choices,
+            negated: false,
+            clue,
+        }
+    }
+
     pub fn choice(&mut self, property: &'a str, choices: Vec<&'a str>) -> &mut Self {
+        let text = format!("choice: {} {}", property, choices.join(", "));
+        let clue = self.add_clue(ClueKind::Choice, text);
         for ch in choices {
-            self.ensure_choice(property, ch);
+            let (prix, chix) = self.ensure_choice(property, ch);
+            let mut zebra = self.zebra.borrow_mut();
+            if zebra.declarations[prix][chix].is_none() {
+                zebra.declarations[prix][chix] = Some(clue);
+            }
+        }
+        self
+    }
+
+    /// Makes the last clue soft: solutions may violate it at the cost of
+    /// `weight`, see `soft::find_least_penalty`.
+    pub fn soft(&mut self, weight: i64) -> &mut Self {
+        if weight <= 0 {
+            panic!("Soft clue weight {} should be positive", weight);
+        }
+        match self.zebra.borrow_mut().clues.last_mut() {
+            Some(clue) if clue.kind != ClueKind::Choice => clue.weight = Some(weight),
+            _ => panic!("Only a clue constraining the objects can be soft"),
         }
         self
     }
 
+    fn add_clue(&mut self, kind: ClueKind, text: String) -> usize {
+        let mut zebra = self.zebra.borrow_mut();
+        zebra.clues.push(Clue {
+            kind,
+            text,
+            weight: None,
+        });
+        zebra.clues.len() - 1
+    }
+
     fn ensure_property(&mut self, property: &'a str) -> usize {
         let pr = self.zebra.borrow().get_property(property);
 
-        if pr.is_none() {
+        if let Some(pr) = pr {
+            pr
+        } else {
             self.zebra.borrow_mut().properties.push(property);
             self.zebra.borrow_mut().options.push(vec![]);
+            self.zebra.borrow_mut().declarations.push(vec![]);
+            self.zebra.borrow_mut().occurrences.push(vec![]);
+            self.zebra.borrow_mut().numeric.push(false);
+            self.zebra.borrow_mut().arrangements.push(Arrangement::Line);
             self.zebra.borrow().get_property_count() - 1
-        } else {
-            pr.unwrap()
         }
     }
 
     fn ensure_choice(&mut self, property: &'a str, choice: &'a str) -> (usize, usize) {
         let prix = self.ensure_property(property);
         let ch = self.zebra.borrow().get_property_choice(property, choice);
-        let chix = if ch.is_none() {
+        let chix = if let Some(ch) = ch {
+            ch
+        } else {
             self.zebra.borrow_mut().options[prix].push(choice);
+            self.zebra.borrow_mut().declarations[prix].push(None);
+            self.zebra.borrow_mut().occurrences[prix].push((1, 1));
             self.zebra.borrow_mut().options[prix].len() - 1
-        } else {
-            ch.unwrap()
         };
 
         (prix, chix)
     }
 }
 
+impl Default for ZebraBuilder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
 
     use super::*;
@@ -467,6 +2219,15 @@ mod tests {
         );
     }
 
+    #[test]
+    #[allow(deprecated)]
+    fn test_fix_first_property() {
+        let mut zebra = make_zebra();
+        zebra.set_choice_enabled(0, 1, 0, true);
+        zebra.fix_first_property();
+        assert_eq!(make_zebra().to_string(), zebra.to_string());
+    }
+
     #[test]
     fn test_state_is_determined() {
         let zebra = make_zebra();
@@ -493,9 +2254,9 @@ mod tests {
     }
 
     #[test]
-    fn test_split() {
+    fn test_branch() {
         let zebra = make_zebra();
-        let zz = zebra.split();
+        let zz = zebra.branch();
 
         assert_eq!(3, zz.len());
         assert_eq!(true, zz[0].is_determined(1, 0));
@@ -522,6 +2283,559 @@ mod tests {
         assert_eq!(2, change_counter);
     }
 
+    #[test]
+    fn test_apply_negative_facts() {
+        let mut zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("p1", vec!["p1a", "p1b", "p1c"])
+            .not_fact("p1", "p1a", "p2", "p2a")
+            .not_fact("p2", "p2b", "p1", "p1b")
+            .choice("p2", vec!["p2c"])
+            .build();
+        let mut change_counter = 0;
+        assert!(zebra.apply_facts(&mut change_counter));
+        assert_eq!(2, change_counter);
+        assert!(!zebra.is_choice_enabled(1, 0, 0));
+        assert!(!zebra.is_choice_enabled(1, 1, 1));
+
+        zebra.determine_choice(1, 0, 0);
+        assert!(!zebra.apply_facts(&mut change_counter));
+    }
+
+    #[test]
+    fn test_apply_relations() {
+        let mut zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("position", vec!["1", "2", "3"])
+            .choice("p2", vec!["p2a", "p2b", "p2c"])
+            .relation(Relation::AtEnd, "position", vec![("p2", "p2a")])
+            .relation(
+                Relation::ImmediatelyRightOf,
+                "position",
+                vec![("p2", "p2b"), ("p2", "p2c")],
+            )
+            .build();
+        let mut change_counter = 0;
+        assert!(zebra.apply_predicates(&mut change_counter));
+        // p2a at an end, p2b has a left neighbour, p2c a right one
+        assert!(!zebra.is_choice_enabled(1, 1, 0));
+        assert!(!zebra.is_choice_enabled(1, 0, 1));
+        assert!(!zebra.is_choice_enabled(1, 2, 2));
+        assert_eq!(3, change_counter);
+
+        zebra.determine_choice(1, 0, 0);
+        assert!(zebra.apply_permutations(&mut change_counter));
+        assert!(zebra.apply_predicates(&mut change_counter));
+        assert_eq!(Some(1), zebra.get_determined(1, 2));
+        assert_eq!(Some(2), zebra.get_determined(1, 1));
+    }
+
+    fn make_disjunction_zebra<'a>(exclusive: bool) -> Zebra<'a> {
+        let literals = vec![
+            Literal::fact("p2", "p2a", "position", "1"),
+            Literal::relation(
+                Relation::NextTo,
+                "position",
+                vec![("p2", "p2a"), ("p2", "p2b")],
+            ),
+        ];
+        let mut builder = ZebraBuilder::new();
+        builder
+            .set_object_count(3)
+            .choice("position", vec!["1", "2", "3"])
+            .choice("p2", vec!["p2a", "p2b", "p2c"]);
+        if exclusive {
+            builder.either(literals);
+        } else {
+            builder.any_of(literals);
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn test_apply_disjunction() {
+        let mut zebra = make_disjunction_zebra(false);
+        let mut change_counter = 0;
+        assert!(zebra.apply_predicates(&mut change_counter));
+        assert_eq!(0, change_counter);
+
+        // p2a is not in the first house, so it must be next to p2b
+        zebra.set_choice_enabled(1, 0, 0, false);
+        zebra.set_choice_enabled(1, 1, 1, false);
+        assert!(zebra.apply_predicates(&mut change_counter));
+        assert!(!zebra.is_choice_enabled(1, 2, 0));
+    }
+
+    #[test]
+    fn test_apply_exclusive_disjunction() {
+        let mut zebra = make_disjunction_zebra(true);
+        zebra.determine_choice(1, 0, 0);
+        let mut change_counter = 0;
+        assert!(zebra.apply_predicates(&mut change_counter));
+        // p2a is in the first house, so p2b is not next to it
+        assert!(!zebra.is_choice_enabled(1, 1, 1));
+
+        zebra.determine_choice(1, 1, 1);
+        assert!(!zebra.apply_predicates(&mut change_counter));
+    }
+
+    #[test]
+    fn test_disjunction_solutions() {
+        use crate::backtracking::find_all;
+        // of 6 orders 4 have p2a next to p2b, 2 have p2a first, 1 has both
+        assert_eq!(5, find_all(make_disjunction_zebra(false)).len());
+        assert_eq!(4, find_all(make_disjunction_zebra(true)).len());
+    }
+
+    fn make_implication_zebra<'a>() -> Zebra<'a> {
+        // if p2a is in the first house then p2b is at an end
+        ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("position", vec!["1", "2", "3"])
+            .choice("p2", vec!["p2a", "p2b", "p2c"])
+            .implies(
+                Literal::fact("p2", "p2a", "position", "1"),
+                Literal::relation(Relation::AtEnd, "position", vec![("p2", "p2b")]),
+            )
+            .build()
+    }
+
+    #[test]
+    fn test_apply_implication() {
+        let mut zebra = make_implication_zebra();
+        let mut change_counter = 0;
+        assert!(zebra.apply_predicates(&mut change_counter));
+        assert_eq!(0, change_counter);
+
+        // modus ponens
+        let mut z = zebra.clone();
+        z.determine_choice(1, 0, 0);
+        assert!(z.apply_predicates(&mut change_counter));
+        assert!(!z.is_choice_enabled(1, 1, 1));
+
+        // modus tollens: p2b in the middle, so p2a is not first
+        zebra.determine_choice(1, 1, 1);
+        assert!(zebra.apply_permutations(&mut change_counter));
+        assert!(zebra.apply_predicates(&mut change_counter));
+        assert!(!zebra.is_choice_enabled(1, 0, 0));
+    }
+
+    #[test]
+    fn test_implication_solutions() {
+        use crate::backtracking::find_all;
+        // of 6 orders only p2a, p2c, p2b starts with p2a
+        assert_eq!(5, find_all(make_implication_zebra()).len());
+    }
+
+    #[test]
+    fn test_cardinality_solutions() {
+        use crate::backtracking::find_all;
+        let at_end = |ch| Literal::relation(Relation::AtEnd, "position", vec![("p2", ch)]);
+        let zebra = ZebraBuilder::new()
+            .set_object_count(4)
+            .choice("position", vec!["1", "2", "3", "4"])
+            .choice("p2", vec!["p2a", "p2b", "p2c", "p2d"])
+            .at_most(1, vec![at_end("p2a"), at_end("p2b"), at_end("p2c")])
+            .build();
+        // p2d at one end, one of the others at the other end
+        assert_eq!(2 * 3 * 2, find_all(zebra).len());
+
+        let zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("p2", vec!["p2a", "p2b", "p2c"])
+            .choice("p3", vec!["p3a", "p3b", "p3c"])
+            .choice("p4", vec!["p4a", "p4b", "p4c"])
+            .object_cardinality(
+                vec![("p3", vec!["p3a", "p3b"]), ("p4", vec!["p4a", "p4b"])],
+                2,
+                2,
+            )
+            .build();
+        assert_eq!(12, find_all(zebra).len());
+    }
+
+    #[test]
+    fn test_apply_object_cardinality() {
+        let mut zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("p2", vec!["p2a", "p2b", "p2c"])
+            .choice("p3", vec!["p3a", "p3b", "p3c"])
+            .object_cardinality(vec![("p2", vec!["p2a"]), ("p3", vec!["p3a", "p3b"])], 0, 0)
+            .build();
+        // object 0 has p2a, so it cannot have p3a or p3b
+        let mut change_counter = 0;
+        assert!(zebra.apply_predicates(&mut change_counter));
+        assert_eq!(Some(2), zebra.get_determined(1, 0));
+
+        zebra.determine_choice(1, 0, 0);
+        assert!(!zebra.apply_predicates(&mut change_counter));
+    }
+
+    // Friends sitting next to each other, counting those who still may
+    // (`possible`) or those who surely do.
+    fn count_friends_next_to(zebra: &Zebra, possible: bool) -> i64 {
+        let friends = [("a", "b"), ("b", "c"), ("c", "d"), ("a", "d"), ("b", "d")];
+        let owners = |name: &str| -> Vec<usize> {
+            let props = zebra.get_properties();
+            let p = props.get_property("name").unwrap();
+            let ch = props.get_property_choice("name", name).unwrap();
+            (0..4)
+                .filter(|o| zebra.is_choice_enabled(p, *o, ch))
+                .collect()
+        };
+        let next_to = |o1: &usize, o2: &usize| o1.abs_diff(*o2) == 1;
+        let count = friends.iter().filter(|(f1, f2)| {
+            let (o1, o2) = (owners(f1), owners(f2));
+            if possible {
+                o1.iter().any(|a| o2.iter().any(|b| next_to(a, b)))
+            } else {
+                o1.iter().all(|a| o2.iter().all(|b| next_to(a, b)))
+            }
+        });
+        count.count() as i64
+    }
+
+    #[test]
+    fn test_find_best() {
+        use crate::backtracking::{find_best, Goal};
+        let zebra = ZebraBuilder::new()
+            .set_object_count(4)
+            .choice("seat", vec!["1", "2", "3", "4"])
+            .choice("name", vec!["a", "b", "c", "d"])
+            .build();
+        let objective = |z: &Zebra| count_friends_next_to(z, false);
+        let best = find_best(zebra.clone(), Goal::Maximize, objective, |z| {
+            count_friends_next_to(z, true)
+        });
+        // a row of 4 has 3 neighbouring pairs, all of them friends
+        assert_eq!(3, count_friends_next_to(&best.unwrap(), false));
+        let worst = find_best(zebra, Goal::Minimize, objective, |z| {
+            count_friends_next_to(z, false)
+        });
+        // a and c are the only pair who are not friends
+        assert_eq!(2, count_friends_next_to(&worst.unwrap(), false));
+    }
+
+    fn make_table_zebra<'a>() -> Zebra<'a> {
+        ZebraBuilder::new()
+            .set_object_count(4)
+            .choice("seat", vec!["1", "2", "3", "4"])
+            .arrangement("seat", Arrangement::Circle)
+            .choice("name", vec!["a", "b", "c", "d"])
+            .relation(
+                Relation::Opposite,
+                "seat",
+                vec![("name", "a"), ("name", "b")],
+            )
+            .relation(Relation::LeftOf, "seat", vec![("name", "c"), ("name", "a")])
+            .build()
+    }
+
+    #[test]
+    fn test_round_table() {
+        use crate::backtracking::find_all;
+        // rotations of the table are a single solution with a in seat 1
+        let solutions = find_all(make_table_zebra());
+        assert_eq!(1, solutions.len());
+        assert_eq!(Some(0), solutions[0].get_determined(1, 0));
+        assert_eq!(Some(1), solutions[0].get_determined(1, 2));
+        // c is left of a across the first seat
+        assert_eq!(Some(2), solutions[0].get_determined(1, 3));
+    }
+
+    #[test]
+    #[should_panic(expected = "Relation Opposite is not defined on Line property seat")]
+    fn test_opposite_on_line() {
+        ZebraBuilder::new()
+            .set_object_count(2)
+            .choice("seat", vec!["1", "2"])
+            .choice("name", vec!["a", "b"])
+            .relation(
+                Relation::Opposite,
+                "seat",
+                vec![("name", "a"), ("name", "b")],
+            )
+            .build();
+    }
+
+    #[test]
+    #[should_panic(expected = "Relation AtEnd is not defined on Circle property seat")]
+    fn test_at_end_on_circle() {
+        ZebraBuilder::new()
+            .set_object_count(2)
+            .choice("seat", vec!["1", "2"])
+            .choice("name", vec!["a", "b"])
+            .either(vec![
+                Literal::fact("name", "a", "seat", "1"),
+                Literal::relation(Relation::AtEnd, "seat", vec![("name", "b")]),
+            ])
+            .arrangement("seat", Arrangement::Circle)
+            .build();
+    }
+
+    #[test]
+    #[should_panic(expected = "Property place cannot be circular")]
+    fn test_second_circle() {
+        ZebraBuilder::new()
+            .arrangement("seat", Arrangement::Circle)
+            .arrangement("place", Arrangement::Circle);
+    }
+
+    #[test]
+    fn test_unused_choices() {
+        use crate::backtracking::find_all;
+        let zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("name", vec!["a", "b", "c"])
+            .choice("pet", vec!["dog", "cat", "fish", "bird"])
+            .occurrence("pet", vec!["dog", "cat", "fish", "bird"], 0, 1)
+            .build();
+        assert_eq!(24, find_all(zebra).len());
+    }
+
+    #[test]
+    fn test_shared_choices() {
+        use crate::backtracking::find_all;
+        // without a fact, or with it in either order of the choices
+        let make = |fact: Option<bool>| {
+            let mut builder = ZebraBuilder::new();
+            builder
+                .set_object_count(3)
+                .choice("name", vec!["a", "b", "c"])
+                .choice("color", vec!["red", "blue"])
+                .occurrence("color", vec!["red"], 2, 2);
+            match fact {
+                Some(false) => builder.fact("name", "a", "color", "red"),
+                Some(true) => builder.fact("color", "red", "name", "a"),
+                None => &mut builder,
+            };
+            builder.build()
+        };
+        assert_eq!(3, find_all(make(None)).len());
+        // a lives in one of the red houses, not necessarily the only one
+        for reversed in [false, true] {
+            let solutions = find_all(make(Some(reversed)));
+            assert_eq!(2, solutions.len());
+            assert!(solutions.iter().all(|s| s.get_determined(1, 0) == Some(0)));
+        }
+    }
+
+    #[test]
+    fn test_optional_before_object_count() {
+        use crate::backtracking::find_all;
+        let zebra = ZebraBuilder::new()
+            .choice("name", vec!["a", "b", "c"])
+            .optional("pet")
+            .set_object_count(3)
+            .choice("pet", vec!["dog", "cat"])
+            .occurrence("pet", vec!["dog", "cat"], 0, 1)
+            .build();
+        // nobody, either one or both of the pets are owned
+        assert_eq!(1 + 3 + 3 + 3 * 2, find_all(zebra).len());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid number choices in property pet is 0 to 2 should be 3")]
+    fn test_too_few_choices() {
+        ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("name", vec!["a", "b", "c"])
+            .choice("pet", vec!["dog", "cat"])
+            .occurrence("pet", vec!["dog", "cat"], 0, 1)
+            .build();
+    }
+
+    #[test]
+    fn test_named_objects() {
+        use crate::backtracking::find_first;
+        let zebra = ZebraBuilder::new()
+            .choice("drink", vec!["tea", "milk", "water"])
+            .objects("name", vec!["Alice", "Bob", "Carol"])
+            .fact("name", "Alice", "drink", "milk")
+            .not_fact("name", "Bob", "drink", "tea")
+            .build();
+        assert_eq!(1, zebra.get_properties().get_identity());
+        assert_eq!(Some("Bob"), zebra.get_determined_name("Bob", "name"));
+        let solution = find_first(zebra).unwrap();
+        assert_eq!(Some("water"), solution.get_determined_name("Bob", "drink"));
+        assert_eq!(Some("Carol"), solution.get_owner_name("drink", "tea"));
+        assert!(solution.to_string().contains("Carol:tea"));
+    }
+
+    fn make_optional_zebra<'a>() -> Zebra<'a> {
+        // the person without a pet lives next to the blue house
+        ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("position", vec!["1", "2", "3"])
+            .choice("pet", vec!["dog", "cat"])
+            .optional("pet")
+            .choice("color", vec!["red", "green", "blue"])
+            .relation(
+                Relation::NextTo,
+                "position",
+                vec![("pet", NONE), ("color", "blue")],
+            )
+            .fact("pet", "dog", "color", "blue")
+            .fact("color", "red", "position", "1")
+            .build()
+    }
+
+    #[test]
+    fn test_optional_choice() {
+        use crate::backtracking::find_all;
+        let solutions = find_all(make_optional_zebra());
+        // blue in 2 with nobody's pet in 1 or 3, or blue in 3 with it in 2
+        assert_eq!(3, solutions.len());
+        for s in &solutions {
+            let none = (0..3).find(|o| s.get_determined(1, *o) == Some(2)).unwrap();
+            let blue = (0..3).find(|o| s.get_determined(2, *o) == Some(2)).unwrap();
+            assert_eq!(1, none.abs_diff(blue));
+        }
+    }
+
+    #[test]
+    fn test_grid_cells() {
+        use crate::backtracking::find_all;
+        let zebra = ZebraBuilder::new()
+            .set_object_count(4)
+            .choice("name", vec!["a", "b", "c", "d"])
+            .grid("floor", vec!["1", "2"], "flat", vec!["left", "right"])
+            .build();
+        // every resident gets a cell of their own
+        assert_eq!(24, find_all(zebra).len());
+    }
+
+    #[test]
+    fn test_grid_relations() {
+        use crate::backtracking::find_all;
+        let zebra = ZebraBuilder::new()
+            .set_object_count(4)
+            .choice("name", vec!["a", "b", "c", "d"])
+            .grid("floor", vec!["1", "2"], "flat", vec!["left", "right"])
+            .grid_relation(
+                GridRelation::DirectlyAbove,
+                "floor",
+                "flat",
+                ("name", "a"),
+                ("name", "b"),
+            )
+            .grid_relation(
+                GridRelation::Diagonal,
+                "floor",
+                "flat",
+                ("name", "c"),
+                ("name", "a"),
+            )
+            .fact("name", "a", "flat", "left")
+            .build();
+        assert_eq!(ClueKind::GridRelation, zebra.get_clue_kind(3));
+        let solutions = find_all(zebra);
+        assert_eq!(1, solutions.len());
+        assert_eq!(Some(0), solutions[0].get_determined(1, 1));
+        assert_eq!(Some(0), solutions[0].get_determined(1, 2));
+        assert_eq!(Some(1), solutions[0].get_determined(2, 2));
+        assert_eq!(Some(1), solutions[0].get_determined(1, 3));
+        assert_eq!(Some(1), solutions[0].get_determined(2, 3));
+    }
+
+    fn make_numeric_zebra<'a>() -> Zebra<'a> {
+        ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("name", vec!["a", "b", "c"])
+            .numeric("age", vec!["30", "20", "25"])
+            .compare("age", Comparison::Greater, ("name", "a"), ("name", "b"))
+            .build()
+    }
+
+    #[test]
+    fn test_apply_comparison() {
+        let mut zebra = make_numeric_zebra();
+        assert_eq!(20, zebra.props.borrow().get_value(1, 1));
+        let mut change_counter = 0;
+        assert!(zebra.apply_predicates(&mut change_counter));
+        assert!(!zebra.is_choice_enabled(1, 0, 1));
+        assert!(!zebra.is_choice_enabled(1, 1, 0));
+        assert_eq!(2, change_counter);
+
+        zebra.determine_choice(1, 0, 2);
+        zebra.determine_choice(1, 1, 0);
+        assert!(!zebra.apply_predicates(&mut change_counter));
+    }
+
+    #[test]
+    fn test_comparison_solutions() {
+        use crate::backtracking::find_all;
+        assert_eq!(3, find_all(make_numeric_zebra()).len());
+
+        let zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("name", vec!["a", "b", "c"])
+            .numeric("age", vec!["30", "20", "25"])
+            .compare("age", Comparison::Greater, ("name", "a"), ("name", "b"))
+            .compare(
+                "age",
+                Comparison::AbsDifference(5),
+                ("name", "a"),
+                ("name", "c"),
+            )
+            .build();
+        assert_eq!(2, find_all(zebra).len());
+    }
+
+    #[test]
+    #[should_panic(expected = "Choice x of numeric property age is not a number")]
+    fn test_numeric_choice_names() {
+        ZebraBuilder::new()
+            .set_object_count(2)
+            .numeric("age", vec!["1", "x"])
+            .build();
+    }
+
+    #[test]
+    fn test_arithmetic_neighbours() {
+        use crate::backtracking::find_all;
+        let red = || Selector::has("color", "red");
+        let zebra = ZebraBuilder::new()
+            .set_object_count(3)
+            .choice("position", vec!["1", "2", "3"])
+            .choice("color", vec!["red", "green", "blue"])
+            .numeric("age", vec!["20", "30", "40"])
+            .arithmetic(
+                Expr::value("age", red().offset("position", -1))
+                    + Expr::value("age", red().offset("position", 1)),
+                Comparison::Equal,
+                Expr::Const(60),
+            )
+            .build();
+
+        // red needs two neighbours, so it is in the middle
+        let mut z = zebra.clone();
+        let mut change_counter = 0;
+        assert!(z.apply_predicates(&mut change_counter));
+        assert_eq!(Some(0), z.get_determined(1, 1));
+
+        // the middle house is 30, the others 20 and 40
+        assert_eq!(4, find_all(zebra).len());
+    }
+
+    #[test]
+    fn test_arithmetic_product() {
+        use crate::backtracking::find_all;
+        let zebra = ZebraBuilder::new()
+            .set_object_count(4)
+            .numeric("position", vec!["1", "2", "3", "4"])
+            .choice("pet", vec!["cat", "dog", "fox", "cow"])
+            .choice("beverage", vec!["coffee", "tea", "milk", "water"])
+            .arithmetic(
+                Expr::value("position", Selector::has("pet", "cat")) * Expr::Const(2),
+                Comparison::Equal,
+                Expr::value("position", Selector::has("beverage", "coffee")),
+            )
+            .build();
+        // cat at 1 and coffee at 2, or cat at 2 and coffee at 4
+        assert_eq!(2 * 6 * 6, find_all(zebra).len());
+    }
+
     #[test]
     fn test_apply_permutations() {
         let mut zebra = make_zebra();
@@ -549,6 +2863,7 @@ mod tests {
         zebra.set_choice_enabled(3, 1, 1, false);
         println!("{}", zebra);
 
-        assert_eq!(true, zebra.apply_predicates());
+        let mut change_counter = 0;
+        assert_eq!(true, zebra.apply_predicates(&mut change_counter));
     }
 }